@@ -6,10 +6,36 @@ const COMMANDS: &[&str] = &[
    "cancel",
    "pause",
    "resume",
+   "set_priority",
+   "move_to_front",
+   "move_before",
+   "set_rate_limit",
+   "set_refresh_listener",
+   "refresh",
+   "subscribe",
+   "unsubscribe",
+   "set_destination",
+   "set_url",
+   "history",
+   "clear_history",
+   "remove_from_history",
    "is_native",
    "registerListener",
 ];
 
+/// An entry of the URL scope granted through the capability system. If no `allow` entries are granted, any URL which
+/// does not match a `deny` entry can be downloaded.
+#[allow(dead_code)]
+#[derive(schemars::JsonSchema)]
+struct UrlScopeEntry {
+   /// Glob pattern of the URLs which can be downloaded, such as `https://*.example.com/**`. Wildcards do not match
+   /// across `/`, except for `**`.
+   url: String,
+}
+
 fn main() {
-   tauri_plugin::Builder::new(COMMANDS).ios_path("ios").build();
+   tauri_plugin::Builder::new(COMMANDS)
+      .global_scope_schema(schemars::schema_for!(UrlScopeEntry))
+      .ios_path("ios")
+      .build();
 }
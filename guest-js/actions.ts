@@ -1,8 +1,8 @@
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
-import { addPluginListener, invoke } from '@tauri-apps/api/core';
+import { addPluginListener, Channel, invoke } from '@tauri-apps/api/core';
 import {
-   AllDownloadActions, allowedActions, Download, DownloadAction, DownloadActionResponse, DownloadState,
-   DownloadStatus, DownloadWithAnyStatus,
+   AllDownloadActions, allowedActions, Checksum, Download, DownloadAction, DownloadActionResponse, DownloadRequest,
+   DownloadState, DownloadStatus, DownloadWithAnyStatus,
 } from './types';
 
 /**
@@ -20,18 +20,18 @@ class DownloadEventManager {
    /**
     * Adds a listener for download events
     *
-    * @param path The path of the download item to listen for
+    * @param key The ID of the download item to listen for, or its path if not yet created
     * @param listener The callback function to invoke when the download changes
     * @returns A promise with a function to remove this specific listener
     */
-   public async addListener(path: string, listener: (download: DownloadWithAnyStatus) => void): Promise<() => void> {
+   public async addListener(key: string, listener: (download: DownloadWithAnyStatus) => void): Promise<() => void> {
       await this._ensureGlobalListeners();
 
-      if (!this._listeners.has(path)) {
-         this._listeners.set(path, new Set());
+      if (!this._listeners.has(key)) {
+         this._listeners.set(key, new Set());
       }
 
-      const listenersForKey = this._listeners.get(path);
+      const listenersForKey = this._listeners.get(key);
 
       if (listenersForKey) {
          listenersForKey.add(listener);
@@ -39,14 +39,14 @@ class DownloadEventManager {
 
       // Return a function to remove this specific listener
       return () => {
-         const listeners = this._listeners.get(path);
+         const listeners = this._listeners.get(key);
 
          if (listeners) {
             listeners.delete(listener);
 
-            // If no more listeners for this path, remove the path from the map.
+            // If no more listeners for this key, remove the key from the map.
             if (listeners.size === 0) {
-               this._listeners.delete(path);
+               this._listeners.delete(key);
             }
          }
 
@@ -65,21 +65,26 @@ class DownloadEventManager {
 
       if (isNative) {
          this._pluginListener = await addPluginListener('download', 'changed', (event: DownloadState<DownloadStatus>) => {
-            this._notifyListeners(event.path, event);
+            this._notifyListeners(event);
          });
       } else {
          this._eventUnlistenFn = await listen<DownloadState<DownloadStatus>>('tauri-plugin-download:changed', (event) => {
-            this._notifyListeners(event.payload.path, event.payload);
+            this._notifyListeners(event.payload);
          });
       }
    }
 
-   private _notifyListeners(path: string, event: DownloadState<DownloadStatus>): void {
-      const listeners = this._listeners.get(path);
+   private _notifyListeners(event: DownloadState<DownloadStatus>): void {
+      // Listeners added before the download was created are keyed by its path.
+      const keys = new Set([ event.id, event.path ]);
 
-      if (listeners) {
-         // eslint-disable-next-line @typescript-eslint/no-use-before-define
-         listeners.forEach((listener) => { return listener(attachDownload(event)); });
+      for (const key of keys) {
+         const listeners = this._listeners.get(key);
+
+         if (listeners) {
+            // eslint-disable-next-line @typescript-eslint/no-use-before-define
+            listeners.forEach((listener) => { return listener(attachDownload(event)); });
+         }
       }
    }
 
@@ -110,27 +115,29 @@ async function sendAction<A extends DownloadAction>(action: A, args: Record<stri
 
 const actions = {
    listen(listener: (download: DownloadWithAnyStatus) => void): Promise<UnlistenFn> {
-      return DownloadEventManager.shared.addListener(this.path, listener);
+      return DownloadEventManager.shared.addListener(this.id || this.path, listener);
    },
 
-   async create(url: string) {
-      return sendAction(DownloadAction.Create, { path: this.path, url });
+   async create(url: string, checksum?: Checksum, request?: DownloadRequest) {
+      return sendAction(DownloadAction.Create, { path: this.path, url, checksum, request });
    },
 
-   async start() {
-      return sendAction(DownloadAction.Start, { path: this.path });
+   async start(onEvent?: (download: DownloadWithAnyStatus) => void) {
+      // eslint-disable-next-line @typescript-eslint/no-use-before-define
+      return sendAction(DownloadAction.Start, { id: this.id, onEvent: onEvent && createChannel(onEvent) });
    },
 
-   async resume() {
-      return sendAction(DownloadAction.Resume, { path: this.path });
+   async resume(onEvent?: (download: DownloadWithAnyStatus) => void) {
+      // eslint-disable-next-line @typescript-eslint/no-use-before-define
+      return sendAction(DownloadAction.Resume, { id: this.id, onEvent: onEvent && createChannel(onEvent) });
    },
 
    async pause() {
-      return sendAction(DownloadAction.Pause, { path: this.path });
+      return sendAction(DownloadAction.Pause, { id: this.id });
    },
 
    async cancel() {
-      return sendAction(DownloadAction.Cancel, { path: this.path });
+      return sendAction(DownloadAction.Cancel, { id: this.id });
    },
 } satisfies AllDownloadActions & ThisType<DownloadState<DownloadStatus>>;
 
@@ -141,10 +148,27 @@ const actions = {
  */
 export function attachDownload<S extends DownloadStatus>(state: DownloadState<S>): Download<S> {
    const download = {
+      id: state.id,
       url: state.url,
       path: state.path,
       progress: state.progress,
       status: state.status,
+      error: state.error,
+      retry: state.retry,
+      queuePosition: state.queuePosition,
+      priority: state.priority,
+      checksum: state.checksum,
+      etag: state.etag,
+      lastModified: state.lastModified,
+      resumable: state.resumable,
+      downloadedBytes: state.downloadedBytes,
+      totalBytes: state.totalBytes,
+      bytesPerSecond: state.bytesPerSecond,
+      etaSeconds: state.etaSeconds,
+      rateLimit: state.rateLimit,
+      request: state.request,
+      segments: state.segments,
+      startedAt: state.startedAt,
    } satisfies DownloadState<S>;
 
    const actionsForDownload = allowedActions[state.status];
@@ -157,3 +181,16 @@ export function attachDownload<S extends DownloadStatus>(state: DownloadState<S>
 
    return download as Download<S>;
 }
+
+/**
+ * Creates a channel which receives the updates of a download.
+ *
+ * @param onEvent The callback function to invoke when the download changes
+ */
+export function createChannel(onEvent: (download: DownloadWithAnyStatus) => void): Channel<DownloadState<DownloadStatus>> {
+   const channel = new Channel<DownloadState<DownloadStatus>>();
+
+   channel.onmessage = (state) => { onEvent(attachDownload(state)); };
+
+   return channel;
+}
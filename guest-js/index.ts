@@ -1,6 +1,10 @@
-import { invoke } from '@tauri-apps/api/core';
-import { DownloadState, DownloadStatus, DownloadWithAnyStatus } from './types';
-import { attachDownload } from './actions';
+import { addPluginListener, invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import {
+   DownloadEvent, DownloadEventType, DownloadRefresh, DownloadState, DownloadStatus, DownloadWithAnyStatus,
+   HistoryPage, HistoryQuery, RefreshReason, RefreshRequest,
+} from './types';
+import { attachDownload, createChannel } from './actions';
 export { attachDownload };
 
 /**
@@ -24,7 +28,7 @@ export async function list(): Promise<DownloadWithAnyStatus[]> {
 }
 
 /**
- * Gets a download by path.
+ * Gets a download by ID or path.
  *
  * If the download exists in the store, returns it. If not found, returns a download in
  * {@link DownloadStatus.Pending} state (not persisted to store).
@@ -32,7 +36,7 @@ export async function list(): Promise<DownloadWithAnyStatus[]> {
  * A `Pending` download can have listeners attached and must be explicitly created via
  * `download.create(url)` to persist it to the store and transition to `Idle` state.
  *
- * @param path - The download path.
+ * @param id - The download ID, or the download path.
  * @returns The download operation.
  *
  * @example
@@ -45,10 +49,275 @@ export async function list(): Promise<DownloadWithAnyStatus[]> {
  * }
  * ```
  */
-export async function get(path: string): Promise<DownloadWithAnyStatus> {
-   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|get', { path });
+export async function get(id: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|get', { id });
 
    return attachDownload(download);
 }
 
+/**
+ * Subscribes to the updates of a download. Unlike {@link Download.listen}, updates are
+ * sent in order through a channel, only to the caller, until the download is completed or
+ * cancelled.
+ *
+ * @param id - The download ID or path.
+ * @param onEvent - Callback function invoked when the download has changed.
+ * @returns A function which unsubscribes from the updates.
+ *
+ * @example
+ * ```ts
+ * const unsubscribe = await subscribe('/path/to/file.zip', (download) => {
+ *    console.log(download.progress);
+ * });
+ * ```
+ */
+export async function subscribe(id: string, onEvent: (download: DownloadWithAnyStatus) => void): Promise<UnlistenFn> {
+   const channel = createChannel(onEvent);
+
+   await invoke('plugin:download|subscribe', { id, onEvent: channel });
+
+   return () => {
+      channel.onmessage = () => { /* unsubscribed */ };
+      void invoke('plugin:download|unsubscribe', { id, channelId: channel.id });
+   };
+}
+
+/**
+ * Listens for an event in the lifecycle of any download, such as its completion. To avoid
+ * memory leaks, the returned function should be called when no longer required.
+ *
+ * @param type - The type of event.
+ * @param handler - Callback function invoked when the event occurs.
+ * @returns A function which stops listening.
+ *
+ * @example
+ * ```ts
+ * const unlisten = await onDownloadEvent(DownloadEventType.Completed, (event) => {
+ *    console.log(`Downloaded ${event.download.path} (${event.size} bytes)`);
+ * });
+ * ```
+ */
+export async function onDownloadEvent<T extends DownloadEventType>(
+   type: T,
+   handler: (event: DownloadEvent<T>) => void
+): Promise<UnlistenFn> {
+   const dispatch = (payload: DownloadEventPayload) => {
+      handler({ ...payload, download: attachDownload(payload.download) } as DownloadEvent<T>);
+   };
+
+   if (await invoke<boolean>('plugin:download|is_native')) {
+      const listener = await addPluginListener('download', type, dispatch);
+
+      return () => { void listener.unregister(); };
+   }
+
+   return listen<DownloadEventPayload>(`tauri-plugin-download:${type}`, (event) => { dispatch(event.payload); });
+}
+
+/**
+ * Sets the priority of a download. Queued downloads with a higher priority are started
+ * before those with a lower priority.
+ *
+ * Not supported on iOS.
+ *
+ * @param id - The download ID or path.
+ * @param priority - The priority. Defaults to `0` for new downloads.
+ * @returns The updated download.
+ */
+export async function setPriority(id: string, priority: number): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|set_priority', { id, priority });
+
+   return attachDownload(download);
+}
+
+/**
+ * Moves a queued download to the front of the queue, so it is the next download to be
+ * started.
+ *
+ * Not supported on iOS.
+ *
+ * @param id - The download ID or path.
+ * @returns The updated download.
+ */
+export async function moveToFront(id: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|move_to_front', { id });
+
+   return attachDownload(download);
+}
+
+/**
+ * Moves a queued download before another queued download. The download takes the
+ * priority of the download it is moved before.
+ *
+ * Not supported on iOS.
+ *
+ * @param id - The download ID or path.
+ * @param beforeId - The ID or path of the queued download to move before.
+ * @returns The updated download.
+ */
+export async function moveBefore(id: string, beforeId: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|move_before', { id, beforeId });
+
+   return attachDownload(download);
+}
+
+/**
+ * Changes the destination of a download which is not in progress. The partial download
+ * (if any) is moved, so the download continues from the same offset.
+ *
+ * Not supported on iOS.
+ *
+ * @param id - The download ID or path.
+ * @param path - The new download path.
+ * @returns The updated download.
+ */
+export async function setDestination(id: string, path: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|set_destination', { id, path });
+
+   return attachDownload(download);
+}
+
+/**
+ * Changes the URL of a download which is not in progress, such as to another mirror. The
+ * partial download (if any) is kept if the new URL serves the same resource, as validated
+ * by its `ETag` and length. Otherwise the download restarts from the beginning when
+ * resumed.
+ *
+ * Not supported on iOS.
+ *
+ * @param id - The download ID or path.
+ * @param url - The new download URL for the resource.
+ * @returns The updated download.
+ */
+export async function setUrl(id: string, url: string): Promise<DownloadWithAnyStatus> {
+   const download = await invoke<DownloadState<DownloadStatus>>('plugin:download|set_url', { id, url });
+
+   return attachDownload(download);
+}
+
+/**
+ * Sets the rate limit of a download, or the total rate limit of all downloads if no ID
+ * is given. The limit applies immediately, including to downloads which are in progress.
+ *
+ * Not supported on iOS.
+ *
+ * @param rateLimit - The limit in bytes per second, or `null` to remove the limit.
+ * @param id - The download ID or path.
+ *
+ * @example
+ * ```ts
+ * // Limit all downloads to a total of 1 MB/s
+ * await setRateLimit(1024 * 1024);
+ *
+ * // Limit a single download to 256 KB/s
+ * await setRateLimit(256 * 1024, '/path/to/file.zip');
+ * ```
+ */
+export async function setRateLimit(rateLimit: number | null, id?: string): Promise<void> {
+   await invoke('plugin:download|set_rate_limit', { id, rateLimit });
+}
+
+/**
+ * Registers a handler which refreshes the request of a download, such as an expired signed
+ * URL or bearer token. The handler is called when the server responds with
+ * `401 Unauthorized` or `403 Forbidden`, before a partial download is resumed, and when the
+ * values of sensitive headers were lost because the application restarted. It returns a new
+ * URL and/or headers, or `null` to leave the request unchanged. The download then continues
+ * from its current offset.
+ *
+ * A hook registered in Rust with `Builder::on_refresh` takes precedence. The handler must
+ * answer within 30 seconds, and is unregistered when the page navigates or reloads. Not
+ * supported on iOS.
+ *
+ * @param handler - The handler.
+ * @returns A function which unregisters the handler.
+ *
+ * @example
+ * ```ts
+ * await onRefresh(async (request) => {
+ *    const token = await fetchToken();
+ *    return {
+ *       headers: { Authorization: `Bearer ${token}` },
+ *       sensitiveHeaders: [ 'Authorization' ],
+ *    };
+ * });
+ * ```
+ */
+export async function onRefresh(
+   handler: (request: RefreshRequest) => Promise<DownloadRefresh | null | undefined>
+): Promise<UnlistenFn> {
+   const unlisten = await listen<RefreshPayload>('tauri-plugin-download:refresh', async (event) => {
+      let refresh: DownloadRefresh | null = null;
+
+      try {
+         refresh = await handler({ ...event.payload, download: attachDownload(event.payload.download) }) ?? null;
+      } catch (error) {
+         console.error('Failed to refresh download request', error);
+      }
+
+      await invoke('plugin:download|refresh', { requestId: event.payload.requestId, refresh });
+   });
+
+   await invoke('plugin:download|set_refresh_listener', { enabled: true });
+
+   return () => {
+      unlisten();
+      void invoke('plugin:download|set_refresh_listener', { enabled: false });
+   };
+}
+
+/**
+ * Gets the finished downloads recorded in the download history, most recently finished
+ * first. The history must be enabled with the `historyPath` setting.
+ *
+ * Not supported on iOS.
+ *
+ * @param query - Filters and paginates the download history.
+ * @returns The page of the download history.
+ *
+ * @example
+ * ```ts
+ * const { records, total } = await history({
+ *    status: DownloadStatus.Completed,
+ *    from: Date.now() - 7 * 24 * 60 * 60 * 1000,
+ *    limit: 20,
+ * });
+ * ```
+ */
+export async function history(query?: HistoryQuery): Promise<HistoryPage> {
+   return invoke<HistoryPage>('plugin:download|history', { query });
+}
+
+/**
+ * Removes all downloads from the download history.
+ *
+ * Not supported on iOS.
+ */
+export async function clearHistory(): Promise<void> {
+   await invoke('plugin:download|clear_history');
+}
+
+/**
+ * Removes a download from the download history.
+ *
+ * Not supported on iOS.
+ *
+ * @param id - The download ID.
+ */
+export async function removeFromHistory(id: string): Promise<void> {
+   await invoke('plugin:download|remove_from_history', { id });
+}
+
+interface DownloadEventPayload {
+   type: DownloadEventType;
+   download: DownloadState<DownloadStatus>;
+}
+
+interface RefreshPayload {
+   requestId: number;
+   reason: RefreshReason;
+   httpStatus?: number;
+   download: DownloadState<DownloadStatus>;
+}
+
 export * from './types';
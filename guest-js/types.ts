@@ -23,6 +23,9 @@ export enum DownloadStatus {
    /** Download has been persisted to the store but has not started downloading. */
    Idle = 'idle',
 
+   /** Download is waiting in the queue to be started. */
+   Queued = 'queued',
+
    /** Download is in progress. */
    InProgress = 'inProgress',
 
@@ -35,10 +38,309 @@ export enum DownloadStatus {
    /** Download has completed successfully. */
    Completed = 'completed',
 
+   /** Download failed. See the `error` field for details. The download can be resumed. */
+   Failed = 'failed',
+
    /** Download status is unknown. */
    Unknown = 'unknown',
 }
 
+/**
+ * Represents the kind of error which caused a download to fail.
+ */
+export enum DownloadErrorKind {
+
+   /** The connection failed or was interrupted. */
+   Network = 'network',
+
+   /** The server returned an unexpected response. */
+   Http = 'http',
+
+   /** The download could not be written to disk. */
+   File = 'file',
+
+   /** The download state could not be persisted. */
+   Store = 'store',
+
+   /** The downloaded file did not match the expected checksum. */
+   Verification = 'verification',
+
+   /** The download could not be resumed as the server does not support partial downloads. */
+   RangeNotSupported = 'rangeNotSupported',
+
+   /** The certificate of the server did not match the pinned public keys of the host. */
+   CertificatePin = 'certificatePin',
+
+   /** The cause of the error is unknown. */
+   Unknown = 'unknown',
+}
+
+/**
+ * Details of the error which caused a download to fail.
+ */
+export interface DownloadError {
+   kind: DownloadErrorKind;
+
+   /** The HTTP status code, if the server returned an unsuccessful response. */
+   httpStatus?: number;
+   message: string;
+
+   /** Time the error occurred, in milliseconds since the Unix epoch. */
+   timestamp: number;
+}
+
+export enum ChecksumAlgorithm {
+   Sha256 = 'sha256',
+   Sha1 = 'sha1',
+   Md5 = 'md5',
+   Blake3 = 'blake3',
+}
+
+/**
+ * Expected checksum of a download, used to verify its integrity.
+ */
+export interface Checksum {
+   algorithm: ChecksumAlgorithm;
+
+   /** The hex digest. */
+   value: string;
+}
+
+/**
+ * The HTTP request sent to download a resource, which is replayed every time the download
+ * is resumed.
+ */
+export interface DownloadRequest {
+
+   /** The HTTP method. Defaults to `GET`. */
+   method?: string;
+
+   /** The request headers, keyed by name. */
+   headers?: Record<string, string>;
+
+   /**
+    * The names of headers with sensitive values, such as `Authorization`. Sensitive values
+    * are only held in memory and are never written to the store, so they must be provided
+    * again through a refresh handler if the application is restarted before the download
+    * completes. Otherwise the download fails when it is started or resumed.
+    */
+   sensitiveHeaders?: string[];
+
+   /** The query parameters, appended to the URL. */
+   query?: Record<string, string>;
+
+   /** The request body. */
+   body?: string;
+}
+
+export enum RefreshReason {
+
+   /** A partial download is about to be resumed. */
+   Resume = 'resume',
+
+   /** The server rejected the request with `401 Unauthorized` or `403 Forbidden`. */
+   Unauthorized = 'unauthorized',
+
+   /**
+    * The values of the sensitive headers are no longer held in memory, such as after the
+    * application restarted.
+    */
+   MissingHeaders = 'missingHeaders',
+}
+
+/**
+ * Request to refresh the request of a download, such as an expired signed URL or bearer
+ * token.
+ */
+export interface RefreshRequest {
+   requestId: number;
+   reason: RefreshReason;
+
+   /** The HTTP status which caused the refresh, if any. */
+   httpStatus?: number;
+   download: DownloadWithAnyStatus;
+}
+
+/**
+ * A refreshed request for a download. The download continues from its current offset.
+ */
+export interface DownloadRefresh {
+
+   /** The new download URL, if changed. */
+   url?: string;
+
+   /** The headers to add or replace, keyed by name. */
+   headers?: Record<string, string>;
+
+   /** The names of headers with sensitive values, which are only held in memory. */
+   sensitiveHeaders?: string[];
+}
+
+/**
+ * Details of a pending retry after a transient error.
+ */
+export interface DownloadRetry {
+
+   /** The retry attempt, starting at 1. */
+   attempt: number;
+   maxAttempts: number;
+
+   /** Time of the next retry, in milliseconds since the Unix epoch. */
+   nextRetryAt: number;
+}
+
+/**
+ * A finished download, recorded in the download history.
+ */
+export interface DownloadRecord {
+   id: string;
+   url: string;
+   path: string;
+
+   /** The outcome of the download, which is either completed or cancelled. */
+   status: DownloadStatus.Completed | DownloadStatus.Cancelled;
+
+   /** Time the download was first started, in milliseconds since the Unix epoch, if it was started. */
+   startedAt?: number;
+
+   /** Time the download was completed or cancelled, in milliseconds since the Unix epoch. */
+   finishedAt: number;
+
+   /**
+    * The size of the downloaded file in bytes, or the number of bytes downloaded before the
+    * download was cancelled.
+    */
+   size: number;
+
+   /** The time since the download was first started in milliseconds, including any time it was paused. */
+   durationMs?: number;
+
+   /**
+    * The average speed over the duration of the download in bytes per second, which includes
+    * any time it was paused so is lower than the transfer speed of a download which was paused.
+    */
+   averageBytesPerSecond?: number;
+}
+
+/**
+ * Filters and paginates the download history.
+ */
+export interface HistoryQuery {
+
+   /** Only include downloads with this outcome. */
+   status?: DownloadStatus.Completed | DownloadStatus.Cancelled;
+
+   /** Only include downloads finished at or after this time, in milliseconds since the Unix epoch. */
+   from?: number;
+
+   /** Only include downloads finished before this time, in milliseconds since the Unix epoch. */
+   to?: number;
+
+   /** The number of matching downloads to skip. */
+   offset?: number;
+
+   /** The maximum number of downloads to return. */
+   limit?: number;
+}
+
+/**
+ * A page of the download history.
+ */
+export interface HistoryPage {
+
+   /** The matching downloads on the page, most recently finished first. */
+   records: DownloadRecord[];
+
+   /** The total number of matching downloads. */
+   total: number;
+}
+
+/**
+ * The type of an event in the lifecycle of a download.
+ */
+export enum DownloadEventType {
+
+   /** The download was created. */
+   Created = 'created',
+
+   /** The download started transferring. */
+   Started = 'started',
+
+   /** More bytes of the download were transferred. */
+   Progress = 'progress',
+
+   /** The download was paused. */
+   Paused = 'paused',
+
+   /** The download started transferring again, continuing from its partial download. */
+   Resumed = 'resumed',
+
+   /** The download failed with a transient error and will be retried. Not emitted on iOS. */
+   Retrying = 'retrying',
+
+   /** The download was completed. */
+   Completed = 'completed',
+
+   /** The download failed. It can be resumed. */
+   Failed = 'failed',
+
+   /** The download was cancelled. */
+   Cancelled = 'cancelled',
+
+   /** The download was removed from the store, after it was completed or cancelled. */
+   Removed = 'removed',
+}
+
+/**
+ * The details of each type of download event, in addition to the download.
+ */
+export interface DownloadEventDetails {
+   [DownloadEventType.Created]: object;
+   [DownloadEventType.Started]: object;
+   [DownloadEventType.Progress]: object;
+   [DownloadEventType.Paused]: object;
+   [DownloadEventType.Resumed]: object;
+   [DownloadEventType.Retrying]: { retry: DownloadRetry; error: DownloadError };
+   [DownloadEventType.Completed]: {
+
+      /** The size of the downloaded file in bytes. */
+      size: number;
+
+      /**
+       * The time since the download was first started in milliseconds, including any time
+       * it was paused. Not available on iOS.
+       */
+      durationMs?: number;
+   };
+
+   /** The error is not available on iOS. */
+   [DownloadEventType.Failed]: { error?: DownloadError };
+   [DownloadEventType.Cancelled]: object;
+   [DownloadEventType.Removed]: object;
+}
+
+/**
+ * An event in the lifecycle of a download.
+ */
+export type DownloadEvent<T extends DownloadEventType = DownloadEventType> = {
+   [K in T]: { type: K; download: DownloadWithAnyStatus } & DownloadEventDetails[K];
+}[T];
+
+/**
+ * A byte range of a segmented download, downloaded in parallel with the other segments.
+ */
+export interface DownloadSegment {
+
+   /** The offset of the first byte of the segment. */
+   start: number;
+
+   /** The offset of the last byte of the segment (inclusive). */
+   end: number;
+
+   /** The number of bytes of the segment downloaded. */
+   downloaded: number;
+}
+
 export enum DownloadAction {
    Listen = 'listen',
    Create = 'create',
@@ -49,10 +351,66 @@ export enum DownloadAction {
 }
 
 export interface DownloadState<S extends DownloadStatus> {
+   /**
+    * The unique ID of the download, generated when it is created. Empty while the download
+    * is `Pending`. On iOS, the ID is the download path.
+    */
+   id: string;
    url: string;
    path: string;
    progress: number;
    status: S;
+   error?: DownloadError;
+   retry?: DownloadRetry;
+
+   /** The position of the download in the queue, while queued. */
+   queuePosition?: number;
+
+   /** The priority of the download. Queued downloads with a higher priority are started first. */
+   priority: number;
+
+   /** The expected checksum, verified before the download is completed. */
+   checksum?: Checksum;
+
+   /** The `ETag` of the resource, used to validate the partial download when resuming. */
+   etag?: string;
+
+   /** The `Last-Modified` date of the resource, used to validate the partial download when resuming. */
+   lastModified?: string;
+
+   /**
+    * Whether the server supports resuming the download, once known. Pausing a download
+    * which is not resumable restarts it from the beginning when resumed, unless configured
+    * otherwise.
+    */
+   resumable?: boolean;
+
+   /** The number of bytes downloaded. */
+   downloadedBytes: number;
+
+   /**
+    * The total size of the download in bytes, if known. If the size is unknown, progress
+    * is indeterminate and `progress` remains `0` until the download completes.
+    */
+   totalBytes?: number;
+
+   /** The smoothed transfer speed, while in progress. */
+   bytesPerSecond?: number;
+
+   /** The estimated time remaining, while in progress and the size is known. */
+   etaSeconds?: number;
+
+   /** The rate limit in bytes per second, if any. */
+   rateLimit?: number;
+
+   /** The request sent to download the resource, if not a plain `GET` request. */
+   request?: DownloadRequest;
+
+   /** The segments downloaded in parallel, if the download is segmented. */
+   segments?: DownloadSegment[];
+
+   /** Time the download was first started, in milliseconds since the Unix epoch. */
+   startedAt?: number;
 }
 
 export interface DownloadActionResponse<A extends DownloadAction = DownloadAction> {
@@ -85,9 +443,25 @@ export interface AllDownloadActions {
     * ```
     */
    [DownloadAction.Listen]: (listener: (download: DownloadWithAnyStatus) => void) => Promise<UnlistenFn>;
-   [DownloadAction.Create]: (url: string) => Promise<DownloadActionResponse<DownloadAction.Create>>;
-   [DownloadAction.Start]: () => Promise<DownloadActionResponse<DownloadAction.Start>>;
-   [DownloadAction.Resume]: () => Promise<DownloadActionResponse<DownloadAction.Resume>>;
+   [DownloadAction.Create]: (url: string, checksum?: Checksum, request?: DownloadRequest) => Promise<DownloadActionResponse<DownloadAction.Create>>;
+
+   /**
+    * Starts the download. If `onEvent` is given, the updates of the download are sent
+    * to it in order through a channel, only to the caller, until the download is completed
+    * or cancelled.
+    *
+    * @param onEvent Callback function invoked when the download has changed.
+    */
+   [DownloadAction.Start]: (onEvent?: (download: DownloadWithAnyStatus) => void) => Promise<DownloadActionResponse<DownloadAction.Start>>;
+
+   /**
+    * Resumes the download. If `onEvent` is given, the updates of the download are sent
+    * to it in order through a channel, only to the caller, until the download is completed
+    * or cancelled.
+    *
+    * @param onEvent Callback function invoked when the download has changed.
+    */
+   [DownloadAction.Resume]: (onEvent?: (download: DownloadWithAnyStatus) => void) => Promise<DownloadActionResponse<DownloadAction.Resume>>;
    [DownloadAction.Pause]: () => Promise<DownloadActionResponse<DownloadAction.Pause>>;
    [DownloadAction.Cancel]: () => Promise<DownloadActionResponse<DownloadAction.Cancel>>;
 }
@@ -103,6 +477,11 @@ export const allowedActions = {
       DownloadAction.Start,
       DownloadAction.Cancel,
    ],
+   [DownloadStatus.Queued]: [
+      DownloadAction.Listen,
+      DownloadAction.Pause,
+      DownloadAction.Cancel,
+   ],
    [DownloadStatus.InProgress]: [
       DownloadAction.Listen,
       DownloadAction.Pause,
@@ -113,6 +492,11 @@ export const allowedActions = {
       DownloadAction.Resume,
       DownloadAction.Cancel,
    ],
+   [DownloadStatus.Failed]: [
+      DownloadAction.Listen,
+      DownloadAction.Resume,
+      DownloadAction.Cancel,
+   ],
    [DownloadStatus.Completed]: [],
    [DownloadStatus.Cancelled]: [],
    [DownloadStatus.Unknown]: [
@@ -122,8 +506,8 @@ export const allowedActions = {
 
 export const expectedStatusesForAction = {
    [DownloadAction.Create]: [ DownloadStatus.Idle ],
-   [DownloadAction.Start]: [ DownloadStatus.InProgress ],
-   [DownloadAction.Resume]: [ DownloadStatus.InProgress ],
+   [DownloadAction.Start]: [ DownloadStatus.Queued, DownloadStatus.InProgress ],
+   [DownloadAction.Resume]: [ DownloadStatus.Queued, DownloadStatus.InProgress ],
    [DownloadAction.Pause]: [ DownloadStatus.Paused ],
    [DownloadAction.Cancel]: [ DownloadStatus.Cancelled ],
 
@@ -131,10 +515,12 @@ export const expectedStatusesForAction = {
    [DownloadAction.Listen]: [
       DownloadStatus.Pending,
       DownloadStatus.Idle,
+      DownloadStatus.Queued,
       DownloadStatus.InProgress,
       DownloadStatus.Paused,
       DownloadStatus.Cancelled,
       DownloadStatus.Completed,
+      DownloadStatus.Failed,
    ],
 } as const satisfies Record<DownloadAction, DownloadStatus[] | []>;
 
@@ -9,13 +9,15 @@ import Foundation
 /// Used to track the status and progress of a download operation.
 public final class DownloadItem: ObservableObject, Identifiable, Codable {
    enum CodingKeys: CodingKey {
-      case url, path, progress, status, resumeDataPath
+      case id, url, path, progress, status, resumeDataPath, downloadedBytes, totalBytes
    }
    
    public let url: URL
    public let path: URL
    @Published public private(set) var progress: Double
    @Published public private(set) var status: DownloadStatus
+   @Published public private(set) var downloadedBytes: Int64 = 0
+   @Published public private(set) var totalBytes: Int64?
    public var resumeDataPath: URL?
    
    init(url: URL, path: URL, progress: Double = 0.0, status: DownloadStatus = .idle, resumeDataPath: URL? = nil) {
@@ -33,11 +35,18 @@ public final class DownloadItem: ObservableObject, Identifiable, Codable {
       progress = try container.decode(Double.self, forKey: .progress)
       status = try container.decode(DownloadStatus.self, forKey: .status)
       resumeDataPath = try container.decodeIfPresent(URL.self, forKey: .resumeDataPath)
+      downloadedBytes = try container.decodeIfPresent(Int64.self, forKey: .downloadedBytes) ?? 0
+      totalBytes = try container.decodeIfPresent(Int64.self, forKey: .totalBytes)
    }
    
    public func setProgress(_ progress: Double) {
       self.progress = progress
    }
+
+   public func setBytes(downloaded: Int64, total: Int64?) {
+      self.downloadedBytes = downloaded
+      self.totalBytes = total
+   }
    
    public func setStatus(_ status: DownloadStatus) {
       self.status = status
@@ -45,10 +54,14 @@ public final class DownloadItem: ObservableObject, Identifiable, Codable {
    
    public func encode(to encoder: Encoder) throws {
       var container = encoder.container(keyedBy: CodingKeys.self)
+      // Downloads are identified by their path, which is encoded as the ID shared with other platforms.
+      try container.encode(path.absoluteString, forKey: .id)
       try container.encode(url, forKey: .url)
       try container.encode(path, forKey: .path)
       try container.encode(progress, forKey: .progress)
       try container.encode(status, forKey: .status)
       try container.encode(resumeDataPath, forKey: .resumeDataPath)
+      try container.encode(downloadedBytes, forKey: .downloadedBytes)
+      try container.encodeIfPresent(totalBytes, forKey: .totalBytes)
    }
 }
@@ -190,7 +190,9 @@ public final class DownloadManager: NSObject, ObservableObject, URLSessionDownlo
       guard let url = downloadTask.originalRequest?.url,
             let item = downloads.first(where: { $0.url == url }) else { return }
 
-      item.setProgress(Double(totalBytesWritten) / Double(totalBytesExpectedToWrite) * 100)
+      let totalBytes = totalBytesExpectedToWrite == NSURLSessionTransferSizeUnknown ? nil : totalBytesExpectedToWrite
+      item.setBytes(downloaded: totalBytesWritten, total: totalBytes)
+      item.setProgress(totalBytes.map { Double(totalBytesWritten) / Double($0) * 100 } ?? 0)
       if let index = self.downloads.firstIndex(where: {$0.path == item.path}) {
          downloads[index] = item
          emitChanged(item)
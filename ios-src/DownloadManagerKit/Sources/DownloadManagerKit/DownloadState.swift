@@ -5,5 +5,5 @@
 
 /// Represents the various states of a download item.
 public enum DownloadStatus: String, Codable, Sendable {
-   case pending, idle, inProgress, paused, cancelled, completed, unknown
+   case pending, idle, queued, inProgress, paused, cancelled, completed, failed, unknown
 }
@@ -8,20 +8,57 @@ class PathArgs: Decodable {
    let path: String
 }
 
+class SubscribeArgs: Decodable {
+   let path: String
+   let onEvent: Channel
+}
+
+class UnsubscribeArgs: Decodable {
+   let path: String
+   let channelId: UInt64
+}
+
 class CreateArgs: Decodable {
    let path: String
    let url: String
 }
 
+struct DownloadEvent: Encodable {
+   let type: String
+   let download: DownloadItem
+   // The size of the downloaded file in bytes, when completed.
+   var size: Int64? = nil
+}
+
 class DownloadPlugin: Plugin {
    let downloadManager = DownloadManager.shared
+   // The channels which receive the updates of each download, keyed by path.
+   private var channels: [URL: [Channel]] = [:]
+   private let channelsLock = NSLock()
+   // The last known status of each download, used to derive its lifecycle events.
+   private var statuses: [URL: DownloadStatus] = [:]
 
    override init()
    {
       super.init()
+      for download in downloadManager.list() {
+         statuses[download.path] = download.status
+      }
+
       Task {
           for await download in DownloadManager.shared.changed {
              try? self.trigger("changed", data: download);
+             self.triggerLifecycleEvents(download)
+
+             // Send the update to the subscribed channels until the download is completed or cancelled.
+             self.channelsLock.withLock {
+                for channel in self.channels[download.path] ?? [] {
+                   try? channel.send(download)
+                }
+                if download.status == .completed || download.status == .cancelled {
+                   self.channels.removeValue(forKey: download.path)
+                }
+             }
 #if DEBUG
              Logger.debug("[\(download.path)] \(download.status) - \(String(format: "%.0f", download.progress))%")
 #endif
@@ -52,6 +89,27 @@ class DownloadPlugin: Plugin {
       invoke.resolve(response)
    }
    
+   @objc public func subscribe(_ invoke: Invoke) throws {
+      let args = try invoke.parseArgs(SubscribeArgs.self)
+      channelsLock.withLock {
+         channels[pathToURL(args.path), default: []].append(args.onEvent)
+      }
+      let response = downloadManager.get(path: pathToURL(args.path))
+      invoke.resolve(response)
+   }
+
+   @objc public func unsubscribe(_ invoke: Invoke) throws {
+      let args = try invoke.parseArgs(UnsubscribeArgs.self)
+      let path = pathToURL(args.path)
+      channelsLock.withLock {
+         channels[path]?.removeAll { $0.id == args.channelId }
+         if channels[path]?.isEmpty == true {
+            channels.removeValue(forKey: path)
+         }
+      }
+      invoke.resolve()
+   }
+
    @objc public func cancel(_ invoke: Invoke) throws {
       let args = try invoke.parseArgs(PathArgs.self)
       let response = try downloadManager.cancel(path: pathToURL(args.path))
@@ -70,6 +128,39 @@ class DownloadPlugin: Plugin {
       invoke.resolve(response)
    }
 
+   private func triggerLifecycleEvents(_ download: DownloadItem) {
+      let previous = statuses.updateValue(download.status, forKey: download.path)
+
+      let type: String?
+      switch download.status {
+      case .idle:
+         type = previous == nil ? "created" : nil
+      case .inProgress:
+         type = previous == .inProgress ? "progress" : (previous == .paused ? "resumed" : "started")
+      case .paused:
+         type = "paused"
+      case .failed:
+         type = "failed"
+      case .completed:
+         type = "completed"
+      case .cancelled:
+         type = "cancelled"
+      default:
+         type = nil
+      }
+
+      if let type = type {
+         let size = download.status == .completed ? download.downloadedBytes : nil
+         try? trigger(type, data: DownloadEvent(type: type, download: download, size: size))
+      }
+
+      // Completed and cancelled downloads are removed from the store.
+      if download.status == .completed || download.status == .cancelled {
+         statuses.removeValue(forKey: download.path)
+         try? trigger("removed", data: DownloadEvent(type: "removed", download: download))
+      }
+   }
+
    private func pathToURL(_ path: String) -> URL {
       // Converts a path string to a URL. Handles both file:// URLs and plain filesystem paths.
       return URL(string: path) ?? URL(fileURLWithPath: path)
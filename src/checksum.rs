@@ -0,0 +1,249 @@
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+
+use crate::{Checksum, ChecksumAlgorithm, Error};
+
+/// Incrementally computes the checksum of a download.
+pub enum ChecksumHasher {
+   Sha256(Sha256),
+   Sha1(Sha1),
+   Md5(Md5),
+   Blake3(Box<blake3::Hasher>),
+}
+
+impl ChecksumHasher {
+   pub fn new(algorithm: &ChecksumAlgorithm) -> Self {
+      match algorithm {
+         ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+         ChecksumAlgorithm::Sha1 => ChecksumHasher::Sha1(Sha1::new()),
+         ChecksumAlgorithm::Md5 => ChecksumHasher::Md5(Md5::new()),
+         ChecksumAlgorithm::Blake3 => ChecksumHasher::Blake3(Box::new(blake3::Hasher::new())),
+      }
+   }
+
+   pub fn update(&mut self, data: &[u8]) {
+      match self {
+         ChecksumHasher::Sha256(hasher) => hasher.update(data),
+         ChecksumHasher::Sha1(hasher) => hasher.update(data),
+         ChecksumHasher::Md5(hasher) => hasher.update(data),
+         ChecksumHasher::Blake3(hasher) => {
+            hasher.update(data);
+         }
+      }
+   }
+
+   ///
+   /// Creates a hasher updated with the contents of a file, such as the partial download being resumed. The file is
+   /// read on a blocking thread, as it may be several gigabytes.
+   ///
+   pub async fn from_file(algorithm: &ChecksumAlgorithm, path: &str) -> crate::Result<Self> {
+      let mut hasher = ChecksumHasher::new(algorithm);
+      let path = path.to_string();
+      tauri::async_runtime::spawn_blocking(move || {
+         hasher.update_from_file(&path)?;
+         Ok(hasher)
+      })
+      .await
+      .map_err(|e| Error::File(format!("Failed to read file: {}", e)))?
+   }
+
+   ///
+   /// Updates the checksum with the contents of a file.
+   ///
+   fn update_from_file(&mut self, path: &str) -> crate::Result<()> {
+      let mut file =
+         File::open(path).map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+      let mut buffer = vec![0; 64 * 1024];
+
+      loop {
+         let read = file
+            .read(&mut buffer)
+            .map_err(|e| Error::File(format!("Failed to read file: {}", e)))?;
+         if read == 0 {
+            return Ok(());
+         }
+
+         self.update(&buffer[..read]);
+      }
+   }
+
+   ///
+   /// Gets the checksum as a lowercase hex digest.
+   ///
+   pub fn finalize(self) -> String {
+      match self {
+         ChecksumHasher::Sha256(hasher) => to_hex(&hasher.finalize()),
+         ChecksumHasher::Sha1(hasher) => to_hex(&hasher.finalize()),
+         ChecksumHasher::Md5(hasher) => to_hex(&hasher.finalize()),
+         ChecksumHasher::Blake3(hasher) => to_hex(hasher.finalize().as_bytes()),
+      }
+   }
+}
+
+///
+/// Validates an expected checksum is a hex digest of the correct length for its algorithm.
+///
+pub fn validate(checksum: &Checksum) -> crate::Result<()> {
+   let expected_len = match checksum.algorithm {
+      ChecksumAlgorithm::Sha256 | ChecksumAlgorithm::Blake3 => 64,
+      ChecksumAlgorithm::Sha1 => 40,
+      ChecksumAlgorithm::Md5 => 32,
+   };
+
+   if checksum.value.len() != expected_len || !checksum.value.chars().all(|c| c.is_ascii_hexdigit())
+   {
+      return Err(Error::Checksum(format!(
+         "Invalid {} digest: {}",
+         checksum.algorithm, checksum.value
+      )));
+   }
+
+   Ok(())
+}
+
+///
+/// Verifies the computed checksum matches the expected checksum.
+///
+pub fn verify(checksum: &Checksum, actual: &str) -> crate::Result<()> {
+   if !checksum.value.eq_ignore_ascii_case(actual) {
+      return Err(Error::Checksum(format!(
+         "Expected {} digest {}, got {}",
+         checksum.algorithm, checksum.value, actual
+      )));
+   }
+
+   Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+   bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{DownloadError, DownloadErrorKind};
+
+   const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+   const ABC_SHA1: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+   const ABC_MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+   const ABC_BLAKE3: &str = "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85";
+
+   fn checksum(algorithm: ChecksumAlgorithm, value: &str) -> Checksum {
+      Checksum {
+         algorithm,
+         value: value.to_string(),
+      }
+   }
+
+   fn digest(algorithm: &ChecksumAlgorithm, chunks: &[&[u8]]) -> String {
+      let mut hasher = ChecksumHasher::new(algorithm);
+      for chunk in chunks {
+         hasher.update(chunk);
+      }
+      hasher.finalize()
+   }
+
+   #[test]
+   fn finalize_gives_known_digests() {
+      assert_eq!(digest(&ChecksumAlgorithm::Sha256, &[b"abc"]), ABC_SHA256);
+      assert_eq!(digest(&ChecksumAlgorithm::Sha1, &[b"abc"]), ABC_SHA1);
+      assert_eq!(digest(&ChecksumAlgorithm::Md5, &[b"abc"]), ABC_MD5);
+      assert_eq!(digest(&ChecksumAlgorithm::Blake3, &[b"abc"]), ABC_BLAKE3);
+   }
+
+   #[test]
+   fn update_is_incremental() {
+      assert_eq!(
+         digest(&ChecksumAlgorithm::Sha256, &[b"a", b"", b"bc"]),
+         ABC_SHA256
+      );
+      assert_eq!(
+         digest(&ChecksumAlgorithm::Blake3, &[b"ab", b"c"]),
+         ABC_BLAKE3
+      );
+   }
+
+   #[test]
+   fn from_file_hashes_file_contents() {
+      let path =
+         std::env::temp_dir().join(format!("tauri-plugin-download-{}", uuid::Uuid::new_v4()));
+      std::fs::write(&path, b"abc").unwrap();
+
+      let hasher = tauri::async_runtime::block_on(ChecksumHasher::from_file(
+         &ChecksumAlgorithm::Sha1,
+         &path.display().to_string(),
+      ));
+      let _ = std::fs::remove_file(&path);
+
+      assert_eq!(hasher.unwrap().finalize(), ABC_SHA1);
+   }
+
+   #[test]
+   fn from_file_fails_for_missing_file() {
+      let path =
+         std::env::temp_dir().join(format!("tauri-plugin-download-{}", uuid::Uuid::new_v4()));
+      let result = tauri::async_runtime::block_on(ChecksumHasher::from_file(
+         &ChecksumAlgorithm::Md5,
+         &path.display().to_string(),
+      ));
+
+      assert!(matches!(result, Err(Error::File(_))));
+   }
+
+   #[test]
+   fn validate_accepts_digests_of_correct_length() {
+      assert!(validate(&checksum(ChecksumAlgorithm::Sha256, ABC_SHA256)).is_ok());
+      assert!(validate(&checksum(ChecksumAlgorithm::Sha1, ABC_SHA1)).is_ok());
+      assert!(validate(&checksum(ChecksumAlgorithm::Md5, ABC_MD5)).is_ok());
+      assert!(
+         validate(&checksum(
+            ChecksumAlgorithm::Blake3,
+            &ABC_BLAKE3.to_uppercase()
+         ))
+         .is_ok()
+      );
+   }
+
+   #[test]
+   fn validate_rejects_invalid_lengths() {
+      assert!(validate(&checksum(ChecksumAlgorithm::Sha256, ABC_SHA1)).is_err());
+      assert!(validate(&checksum(ChecksumAlgorithm::Sha1, ABC_MD5)).is_err());
+      assert!(validate(&checksum(ChecksumAlgorithm::Md5, ABC_SHA256)).is_err());
+      assert!(validate(&checksum(ChecksumAlgorithm::Blake3, &ABC_BLAKE3[1..])).is_err());
+      assert!(validate(&checksum(ChecksumAlgorithm::Md5, "")).is_err());
+   }
+
+   #[test]
+   fn validate_rejects_invalid_characters() {
+      let value = format!("{}g", &ABC_MD5[1..]);
+      assert!(matches!(
+         validate(&checksum(ChecksumAlgorithm::Md5, &value)),
+         Err(Error::Checksum(_))
+      ));
+
+      let value = format!("{} ", &ABC_SHA1[1..]);
+      assert!(validate(&checksum(ChecksumAlgorithm::Sha1, &value)).is_err());
+   }
+
+   #[test]
+   fn verify_ignores_case() {
+      let expected = checksum(ChecksumAlgorithm::Sha256, &ABC_SHA256.to_uppercase());
+      assert!(verify(&expected, ABC_SHA256).is_ok());
+   }
+
+   #[test]
+   fn verify_mismatch_is_verification_error() {
+      let expected = checksum(ChecksumAlgorithm::Md5, ABC_MD5);
+      let error = verify(&expected, &digest(&ChecksumAlgorithm::Md5, &[b"abd"])).unwrap_err();
+
+      assert!(matches!(error, Error::Checksum(_)));
+      assert_eq!(
+         DownloadError::from(&error).kind,
+         DownloadErrorKind::Verification
+      );
+   }
+}
@@ -1,4 +1,6 @@
-use tauri::{AppHandle, Runtime, command};
+use std::sync::Arc;
+use tauri::ipc::{Channel, CommandScope, GlobalScope, JavaScriptChannelId};
+use tauri::{AppHandle, Runtime, Webview, command};
 
 use crate::DownloadExt;
 use crate::Result;
@@ -10,8 +12,8 @@ pub(crate) async fn list<R: Runtime>(app: AppHandle<R>) -> Result<Vec<DownloadIt
 }
 
 #[command]
-pub(crate) async fn get<R: Runtime>(app: AppHandle<R>, path: String) -> Result<DownloadItem> {
-   app.download().get(app.clone(), path)
+pub(crate) async fn get<R: Runtime>(app: AppHandle<R>, id: String) -> Result<DownloadItem> {
+   app.download().get(app.clone(), id)
 }
 
 #[command]
@@ -19,40 +21,211 @@ pub(crate) async fn create<R: Runtime>(
    app: AppHandle<R>,
    path: String,
    url: String,
+   checksum: Option<Checksum>,
+   request: Option<DownloadRequest>,
+   command_scope: CommandScope<UrlScopeEntry>,
+   global_scope: GlobalScope<UrlScopeEntry>,
 ) -> Result<DownloadActionResponse> {
-   app.download().create(app.clone(), path, url)
+   let scope = capability_scope(command_scope, global_scope);
+   app.download()
+      .create(app.clone(), path, url, checksum, request, Some(scope))
 }
 
 #[command]
 pub(crate) async fn start<R: Runtime>(
    app: AppHandle<R>,
-   path: String,
+   webview: Webview<R>,
+   id: String,
+   on_event: Option<JavaScriptChannelId>,
+   command_scope: CommandScope<UrlScopeEntry>,
+   global_scope: GlobalScope<UrlScopeEntry>,
 ) -> Result<DownloadActionResponse> {
-   app.download().start(app.clone(), path)
+   let scope = capability_scope(command_scope, global_scope);
+   app.download().check_scope(app.clone(), id.clone(), scope)?;
+   if let Some(channel) = on_event {
+      let channel = channel.channel_on(webview);
+      app.download().subscribe(app.clone(), id.clone(), channel)?;
+   }
+   app.download().start(app.clone(), id)
 }
 
 #[command]
 pub(crate) async fn resume<R: Runtime>(
    app: AppHandle<R>,
-   path: String,
+   webview: Webview<R>,
+   id: String,
+   on_event: Option<JavaScriptChannelId>,
+   command_scope: CommandScope<UrlScopeEntry>,
+   global_scope: GlobalScope<UrlScopeEntry>,
 ) -> Result<DownloadActionResponse> {
-   app.download().resume(app.clone(), path)
+   let scope = capability_scope(command_scope, global_scope);
+   app.download().check_scope(app.clone(), id.clone(), scope)?;
+   if let Some(channel) = on_event {
+      let channel = channel.channel_on(webview);
+      app.download().subscribe(app.clone(), id.clone(), channel)?;
+   }
+   app.download().resume(app.clone(), id)
 }
 
 #[command]
-pub(crate) async fn pause<R: Runtime>(
+pub(crate) async fn subscribe<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
+   on_event: Channel<DownloadItem>,
+) -> Result<DownloadItem> {
+   app.download().subscribe(app.clone(), id, on_event)
+}
+
+#[command]
+pub(crate) async fn unsubscribe<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
+   channel_id: u32,
+) -> Result<()> {
+   app.download().unsubscribe(app.clone(), id, channel_id)
+}
+
+#[command]
+pub(crate) async fn set_destination<R: Runtime>(
    app: AppHandle<R>,
+   id: String,
    path: String,
+) -> Result<DownloadItem> {
+   app.download().set_destination(app.clone(), id, path)
+}
+
+#[command]
+pub(crate) async fn set_url<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
+   url: String,
+   command_scope: CommandScope<UrlScopeEntry>,
+   global_scope: GlobalScope<UrlScopeEntry>,
+) -> Result<DownloadItem> {
+   let scope = capability_scope(command_scope, global_scope);
+   app.download()
+      .set_url(app.clone(), id, url, Some(scope))
+      .await
+}
+
+#[command]
+pub(crate) async fn history<R: Runtime>(
+   app: AppHandle<R>,
+   query: Option<HistoryQuery>,
+) -> Result<HistoryPage> {
+   app.download()
+      .history(app.clone(), query.unwrap_or_default())
+}
+
+#[command]
+pub(crate) async fn clear_history<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+   app.download().clear_history(app.clone())
+}
+
+#[command]
+pub(crate) async fn remove_from_history<R: Runtime>(app: AppHandle<R>, id: String) -> Result<()> {
+   app.download().remove_from_history(app.clone(), id)
+}
+
+#[command]
+pub(crate) async fn pause<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
 ) -> Result<DownloadActionResponse> {
-   app.download().pause(app.clone(), path)
+   app.download().pause(app.clone(), id)
 }
 
 #[command]
 pub(crate) async fn cancel<R: Runtime>(
    app: AppHandle<R>,
-   path: String,
+   id: String,
 ) -> Result<DownloadActionResponse> {
-   app.download().cancel(app.clone(), path)
+   app.download().cancel(app.clone(), id)
+}
+
+#[command]
+pub(crate) async fn set_priority<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
+   priority: i32,
+) -> Result<DownloadItem> {
+   app.download().set_priority(app.clone(), id, priority)
+}
+
+#[command]
+pub(crate) async fn move_to_front<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
+) -> Result<DownloadItem> {
+   app.download().move_to_front(app.clone(), id)
+}
+
+#[command]
+pub(crate) async fn move_before<R: Runtime>(
+   app: AppHandle<R>,
+   id: String,
+   before_id: String,
+) -> Result<DownloadItem> {
+   app.download().move_before(app.clone(), id, before_id)
+}
+
+#[command]
+pub(crate) async fn set_rate_limit<R: Runtime>(
+   app: AppHandle<R>,
+   id: Option<String>,
+   rate_limit: Option<u64>,
+) -> Result<()> {
+   app.download().set_rate_limit(app.clone(), id, rate_limit)
+}
+
+#[command]
+pub(crate) async fn set_refresh_listener<R: Runtime>(
+   app: AppHandle<R>,
+   webview: Webview<R>,
+   enabled: bool,
+) -> Result<()> {
+   app.download()
+      .set_refresh_listener(webview.label().to_string(), enabled)
+}
+
+#[command]
+pub(crate) async fn refresh<R: Runtime>(
+   app: AppHandle<R>,
+   request_id: u64,
+   refresh: Option<DownloadRefresh>,
+   command_scope: CommandScope<UrlScopeEntry>,
+   global_scope: GlobalScope<UrlScopeEntry>,
+) -> Result<()> {
+   // Leave the request unchanged if the refreshed URL is not allowed for the calling window.
+   let scope = capability_scope(command_scope, global_scope);
+   if let Some(url) = refresh.as_ref().and_then(|refresh| refresh.url.as_ref())
+      && let Err(e) = scope.check(url)
+   {
+      app.download().refresh(request_id, None)?;
+      return Err(e);
+   }
+
+   app.download().refresh(request_id, refresh)
+}
+
+///
+/// Combines the URLs granted to the calling window for a command and for all commands of the plugin.
+///
+fn capability_scope(
+   command_scope: CommandScope<UrlScopeEntry>,
+   global_scope: GlobalScope<UrlScopeEntry>,
+) -> CapabilityScope {
+   let entries = |command: &Vec<Arc<UrlScopeEntry>>, global: &Vec<Arc<UrlScopeEntry>>| {
+      command
+         .iter()
+         .chain(global)
+         .map(|entry| UrlScopeEntry::clone(entry))
+         .collect()
+   };
+   CapabilityScope {
+      allow: entries(command_scope.allows(), global_scope.allows()),
+      deny: entries(command_scope.denies(), global_scope.denies()),
+   }
 }
 
 #[tauri::command(rename_all = "snake_case")]
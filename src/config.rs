@@ -0,0 +1,288 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tauri::utils::config::FsScope;
+use tauri_plugin_http::reqwest;
+
+/// Plugin configuration, deserialized from `plugins.download` in `tauri.conf.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+   /// The policy for retrying downloads after a transient error.
+   pub retry: RetryPolicy,
+   /// The maximum number of downloads which can run at once. Further downloads are queued.
+   pub max_concurrent: usize,
+   /// The maximum number of downloads which can run at once for specific hosts, keyed by host name.
+   pub max_concurrent_per_host: HashMap<String, usize>,
+   /// How to resume a download when the server does not support partial downloads.
+   pub range_policy: RangePolicy,
+   /// The total rate limit of all downloads in bytes per second, if any.
+   pub rate_limit: Option<u64>,
+   /// The maximum number of segments a download is split into and downloaded in parallel. Set to 1 to disable
+   /// segmented downloads.
+   pub segments: usize,
+   /// The minimum size of each segment in bytes. Smaller downloads are split into fewer segments.
+   pub min_segment_size: u64,
+   /// The file name of the store which persists downloads.
+   pub store_path: String,
+   /// The file name of the store which records finished downloads, if any. The download history is disabled if not
+   /// set.
+   pub history_path: Option<String>,
+   /// The suffix appended to the path of a partial download.
+   pub temp_suffix: String,
+   /// The minimum increase in progress, in percent, before a progress update is emitted.
+   pub progress_threshold: f64,
+   /// The maximum time between progress updates in milliseconds.
+   pub progress_interval_ms: u64,
+   /// The timeout for establishing a connection in milliseconds, if any.
+   pub connect_timeout_ms: Option<u64>,
+   /// The timeout for each read of a response in milliseconds, if any.
+   pub read_timeout_ms: Option<u64>,
+   /// The `User-Agent` header sent with each request, if any.
+   pub user_agent: Option<String>,
+   /// The proxy for all requests, such as `http://proxy:8080` or `socks5://proxy:1080`. If not set, the system proxy
+   /// (if any) is used.
+   pub proxy: Option<String>,
+   /// The hosts which bypass the proxy, separated by commas.
+   pub no_proxy: Option<String>,
+   /// The maximum number of redirects to follow. Set to 0 to disable redirects. Each redirect must be allowed by the
+   /// URL scope.
+   pub max_redirects: usize,
+   /// The time in milliseconds without receiving any bytes after which a transfer fails, if any. The transfer is
+   /// retried if timeouts are considered transient.
+   pub stall_timeout_ms: Option<u64>,
+   /// Additional trusted root certificates, as PEM encoded certificates or paths to PEM files.
+   pub root_certificates: Vec<String>,
+   /// The pinned public keys of hosts, keyed by host name. Each pin is the base64 encoded SHA-256 hash of the
+   /// `SubjectPublicKeyInfo` of a certificate. Connections to a pinned host fail unless its certificate matches one of
+   /// the pins.
+   pub certificate_pins: HashMap<String, Vec<String>>,
+   /// The paths downloads can be written to, which may use path variables such as `$DOWNLOAD`. If not set, downloads
+   /// can be written to any path.
+   pub destination_scope: Option<FsScope>,
+   /// The URLs which can be downloaded, checked when a download is created and on every redirect.
+   pub url_scope: UrlScope,
+}
+
+impl Default for Config {
+   fn default() -> Self {
+      Self {
+         retry: RetryPolicy::default(),
+         max_concurrent: 4,
+         max_concurrent_per_host: HashMap::new(),
+         range_policy: RangePolicy::default(),
+         rate_limit: None,
+         segments: 1,
+         min_segment_size: 16 * 1024 * 1024,
+         store_path: "downloads.json".to_string(),
+         history_path: None,
+         temp_suffix: ".download".to_string(),
+         progress_threshold: 1.0,
+         progress_interval_ms: 1_000,
+         connect_timeout_ms: None,
+         read_timeout_ms: None,
+         user_agent: None,
+         proxy: None,
+         no_proxy: None,
+         max_redirects: 10,
+         stall_timeout_ms: None,
+         root_certificates: Vec::new(),
+         certificate_pins: HashMap::new(),
+         destination_scope: None,
+         url_scope: UrlScope::default(),
+      }
+   }
+}
+
+/// Restricts the URLs which can be downloaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UrlScope {
+   /// The allowed URL schemes.
+   pub schemes: Vec<String>,
+   /// Glob patterns of the allowed hosts, such as `*.example.com`. If empty, any host is allowed.
+   pub allow_hosts: Vec<String>,
+   /// Glob patterns of the denied hosts, which take precedence over the allowed hosts.
+   pub deny_hosts: Vec<String>,
+   /// The allowed ports, such as `443`, or ranges of ports, such as `8000-8999`. If empty, any port is allowed.
+   pub ports: Vec<String>,
+   /// Whether to deny hosts which are or resolve to loopback, private, link-local or other non-public IP addresses.
+   pub deny_private_ips: bool,
+}
+
+impl Default for UrlScope {
+   fn default() -> Self {
+      Self {
+         schemes: vec!["http".to_string(), "https".to_string()],
+         allow_hosts: Vec::new(),
+         deny_hosts: Vec::new(),
+         ports: Vec::new(),
+         deny_private_ips: false,
+      }
+   }
+}
+
+/// Policy for resuming a download when the server does not support partial downloads.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RangePolicy {
+   /// Restart the download from the beginning.
+   #[default]
+   Restart,
+   /// Fail the download.
+   Fail,
+   /// Pause the download.
+   Pause,
+}
+
+/// Policy for automatically retrying a download after a transient error.
+///
+/// The delay before each retry grows exponentially from `base_delay_ms`, is capped at `max_delay_ms` and is
+/// randomly reduced by up to `jitter` (a fraction between 0 and 1) to avoid many downloads retrying at once.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RetryPolicy {
+   /// The maximum number of consecutive retries before the download fails. Set to 0 to disable retries.
+   pub max_attempts: u32,
+   pub base_delay_ms: u64,
+   pub max_delay_ms: u64,
+   pub jitter: f64,
+   /// HTTP status codes which are considered transient.
+   pub retry_statuses: Vec<u16>,
+   /// Request errors which are considered transient.
+   pub retry_errors: Vec<TransientErrorKind>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransientErrorKind {
+   /// The connection could not be established.
+   Connect,
+   /// The request or response timed out.
+   Timeout,
+   /// The request could not be sent.
+   Request,
+   /// The connection was interrupted while reading the response body.
+   Body,
+   /// The response body could not be decoded.
+   Decode,
+}
+
+impl Default for RetryPolicy {
+   fn default() -> Self {
+      Self {
+         max_attempts: 5,
+         base_delay_ms: 1_000,
+         max_delay_ms: 60_000,
+         jitter: 0.5,
+         retry_statuses: vec![408, 429, 500, 502, 503, 504],
+         retry_errors: vec![
+            TransientErrorKind::Connect,
+            TransientErrorKind::Timeout,
+            TransientErrorKind::Request,
+            TransientErrorKind::Body,
+         ],
+      }
+   }
+}
+
+impl RetryPolicy {
+   ///
+   /// Gets the delay before the given retry attempt (starting at 1).
+   ///
+   pub fn delay(&self, attempt: u32) -> Duration {
+      let exponent = attempt.saturating_sub(1).min(32);
+      let delay_ms = self
+         .base_delay_ms
+         .saturating_mul(1 << exponent)
+         .min(self.max_delay_ms);
+
+      // Randomly reduce the delay by up to the jitter fraction.
+      let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+      let jitter = self.jitter.clamp(0.0, 1.0) * random;
+      Duration::from_millis((delay_ms as f64 * (1.0 - jitter)) as u64)
+   }
+
+   ///
+   /// Whether the HTTP status code is considered transient.
+   ///
+   pub fn is_transient_status(&self, status: u16) -> bool {
+      self.retry_statuses.contains(&status)
+   }
+
+   ///
+   /// Whether the request error is considered transient.
+   ///
+   pub fn is_transient_error(&self, error: &reqwest::Error) -> bool {
+      self.retry_errors.iter().any(|kind| match kind {
+         TransientErrorKind::Connect => error.is_connect(),
+         TransientErrorKind::Timeout => error.is_timeout(),
+         TransientErrorKind::Request => error.is_request(),
+         TransientErrorKind::Body => error.is_body(),
+         TransientErrorKind::Decode => error.is_decode(),
+      })
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn policy(jitter: f64) -> RetryPolicy {
+      RetryPolicy {
+         base_delay_ms: 1_000,
+         max_delay_ms: 60_000,
+         jitter,
+         ..RetryPolicy::default()
+      }
+   }
+
+   #[test]
+   fn delay_doubles_each_attempt() {
+      let policy = policy(0.0);
+      assert_eq!(policy.delay(0), Duration::from_secs(1));
+      assert_eq!(policy.delay(1), Duration::from_secs(1));
+      assert_eq!(policy.delay(2), Duration::from_secs(2));
+      assert_eq!(policy.delay(3), Duration::from_secs(4));
+      assert_eq!(policy.delay(6), Duration::from_secs(32));
+   }
+
+   #[test]
+   fn delay_is_capped() {
+      let policy = policy(0.0);
+      assert_eq!(policy.delay(7), Duration::from_secs(60));
+      assert_eq!(policy.delay(33), Duration::from_secs(60));
+      assert_eq!(policy.delay(u32::MAX), Duration::from_secs(60));
+
+      let policy = RetryPolicy {
+         base_delay_ms: u64::MAX,
+         max_delay_ms: u64::MAX,
+         ..policy
+      };
+      assert_eq!(policy.delay(u32::MAX), Duration::from_millis(u64::MAX));
+   }
+
+   #[test]
+   fn delay_jitter_is_bounded() {
+      let exact = policy(0.0);
+      let jittered = policy(0.5);
+      for attempt in 1..=10 {
+         let max = exact.delay(attempt);
+         for _ in 0..100 {
+            let delay = jittered.delay(attempt);
+            assert!(
+               delay <= max && delay >= max / 2,
+               "{:?} is not within {:?}",
+               delay,
+               max
+            );
+         }
+      }
+
+      // The jitter is clamped to a fraction between 0 and 1.
+      assert_eq!(policy(-1.0).delay(3), Duration::from_secs(4));
+      assert!(policy(2.0).delay(3) <= Duration::from_secs(4));
+   }
+}
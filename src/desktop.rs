@@ -1,36 +1,139 @@
-use futures::StreamExt;
-use serde::de::DeserializeOwned;
+use futures::future::{AbortHandle, Abortable};
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::AppHandle;
+use tauri::ipc::Channel;
 use tauri::{Emitter, Runtime, plugin::PluginApi};
 use tauri_plugin_http::reqwest;
-use tauri_plugin_http::reqwest::header::{HeaderMap, RANGE};
+use tauri_plugin_http::reqwest::header::{
+   ACCEPT_RANGES, AUTHORIZATION, CONTENT_LENGTH, CONTENT_RANGE, COOKIE, ETAG, HeaderMap,
+   HeaderName, HeaderValue, IF_RANGE, LAST_MODIFIED, LOCATION, PROXY_AUTHORIZATION, RANGE,
+   RETRY_AFTER,
+};
+use tauri_plugin_http::reqwest::redirect;
+use tauri_plugin_http::reqwest::{StatusCode, Url};
+use tokio::sync::{broadcast, oneshot};
 
-use crate::Error;
+use crate::checksum::{self, ChecksumHasher};
+use crate::history;
+use crate::scheduler::Scheduler;
+use crate::scope::{self, PathScope, PublicResolver, UrlFilter};
+use crate::throttle::RateLimiter;
+use crate::tls;
+use crate::{
+   Config, DownloadExt, DownloadHandle, Error, RangePolicy, RefreshHook, TransientErrorKind,
+};
 use crate::{models::*, store};
 
-static DOWNLOAD_SUFFIX: &str = ".download";
+mod segments;
 
-pub fn init<R: Runtime, C: DeserializeOwned>(
+const SPEED_SMOOTHING: f64 = 0.3; // Weight of the latest sample in the smoothed speed.
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(30); // Time to wait for a refresh from JavaScript.
+const EVENT_CAPACITY: usize = 256; // Number of events buffered for each Rust subscriber.
+
+/// Callback invoked with each event in the lifecycle of a download.
+type EventCallback = Arc<dyn Fn(&DownloadEvent) + Send + Sync>;
+
+pub fn init<R: Runtime>(
    app: &AppHandle<R>,
-   _api: PluginApi<R, C>,
+   api: PluginApi<R, Option<Config>>,
+   configure: impl FnOnce(&mut Config),
+   refresh_hook: Option<RefreshHook<R>>,
 ) -> crate::Result<Download<R>> {
-   Ok(Download(app.clone()))
+   // Settings set on the builder take precedence over the plugin configuration.
+   let mut config = api.config().clone().unwrap_or_default();
+   configure(&mut config);
+   Ok(Download {
+      app: app.clone(),
+      client: client(&config)?,
+      path_scope: PathScope::new(app, config.destination_scope.as_ref())?,
+      url_filter: UrlFilter::new(&config.url_scope)?,
+      rate_limiter: RateLimiter::new(config.rate_limit),
+      rate_limiters: Mutex::new(HashMap::new()),
+      tasks: Mutex::new(HashMap::new()),
+      sensitive_headers: Mutex::new(HashMap::new()),
+      refresh_hook,
+      refresh_listeners: Mutex::new(HashMap::new()),
+      refresh_requests: Mutex::new(HashMap::new()),
+      next_refresh_id: AtomicU64::new(1),
+      channels: Mutex::new(HashMap::new()),
+      events: broadcast::channel(EVENT_CAPACITY).0,
+      event_callbacks: Mutex::new(HashMap::new()),
+      next_event_callback_id: AtomicU64::new(1),
+      config,
+      scheduler: Scheduler::default(),
+   })
 }
 
 /// Access to the download APIs.
-pub struct Download<R: Runtime>(AppHandle<R>);
+pub struct Download<R: Runtime> {
+   app: AppHandle<R>,
+   config: Config,
+   /// The HTTP client shared by all downloads.
+   client: reqwest::Client,
+   /// Restricts the paths downloads can be written to.
+   path_scope: PathScope,
+   /// Restricts the URLs downloads can fetch.
+   url_filter: UrlFilter,
+   scheduler: Scheduler,
+   /// Limits the total rate of all running downloads.
+   rate_limiter: RateLimiter,
+   /// Limits the rate of each running download, keyed by ID.
+   rate_limiters: Mutex<HashMap<String, Arc<RateLimiter>>>,
+   /// Aborts the task of each running download, keyed by ID. A task is registered until it has finished.
+   tasks: Mutex<HashMap<String, AbortHandle>>,
+   /// The sensitive header values of each download, keyed by ID. These are only held in memory.
+   sensitive_headers: Mutex<HashMap<String, HashMap<String, String>>>,
+   /// The hook which refreshes the request of a download, if registered in Rust.
+   refresh_hook: Option<RefreshHook<R>>,
+   /// The number of hooks which refresh the request of a download registered in JavaScript, keyed by webview label.
+   refresh_listeners: Mutex<HashMap<String, usize>>,
+   /// The refresh requests awaiting an answer from JavaScript, keyed by request ID.
+   refresh_requests: Mutex<HashMap<u64, oneshot::Sender<Option<DownloadRefresh>>>>,
+   next_refresh_id: AtomicU64,
+   /// The channels which receive the updates of each download, keyed by ID.
+   channels: Mutex<HashMap<String, Vec<Channel<DownloadItem>>>>,
+   /// Sends the lifecycle events of all downloads to Rust subscribers.
+   events: broadcast::Sender<DownloadEvent>,
+   /// The callbacks registered in Rust which receive the lifecycle events of all downloads, keyed by ID.
+   event_callbacks: Mutex<HashMap<u64, EventCallback>>,
+   next_event_callback_id: AtomicU64,
+}
+
+/// Error which caused a single transfer attempt to fail.
+enum TransferError {
+   /// An error which may succeed if retried, with an optional delay requested by the server.
+   Transient(Error, Option<Duration>),
+   /// An error which will not succeed if retried.
+   Permanent(Error),
+}
+
+impl From<Error> for TransferError {
+   fn from(error: Error) -> Self {
+      TransferError::Permanent(error)
+   }
+}
 
 impl<R: Runtime> Download<R> {
    ///
    /// Initializes the API.
-   /// Updates the state of any download operations which are still marked as "In Progress". This can occur if the
-   /// application was suspended or terminated before a download was completed.
+   /// Migrates any download operations stored by path to be stored by ID, then updates the state of any download
+   /// operations which are still marked as "In Progress". This can occur if the application was suspended or
+   /// terminated before a download was completed. Queued download operations are then started, up to the concurrency
+   /// limit.
    ///
    pub fn init(&self) {
-      let items = match store::list(&self.0) {
+      if let Err(e) = store::migrate(&self.app) {
+         eprintln!("Failed to migrate download store: {}", e);
+      }
+
+      let items = match store::list(&self.app) {
          Ok(list) => list,
          Err(e) => {
             eprintln!("Failed to load download store: {}", e);
@@ -42,19 +145,21 @@ impl<R: Runtime> Download<R> {
          .into_iter()
          .filter(|item| item.status == DownloadStatus::InProgress)
       {
-         let new_status = if item.progress == 0.0 {
+         let new_status = if item.progress == 0.0 && item.downloaded_bytes == 0 {
             DownloadStatus::Idle
          } else {
             DownloadStatus::Paused
          };
 
-         if let Err(e) = store::update(&self.0, item.with_status(new_status.clone())) {
+         if let Err(e) = store::update(&self.app, item.with_status(new_status.clone())) {
             eprintln!("[{}] Failed to update download status: {}", &item.path, e);
             continue;
          }
 
          println!("[{}] Found download item - {}", &item.path, new_status);
       }
+
+      Download::schedule(&self.app);
    }
 
    ///
@@ -77,18 +182,19 @@ impl<R: Runtime> Download<R> {
    /// persist it and transition to `Idle` state.
    ///
    /// # Arguments
-   /// - `path` - The download path.
+   /// - `id` - The download ID, or the download path.
    ///
    /// # Returns
    /// The download operation.
-   pub fn get(&self, _app: AppHandle<R>, path: String) -> crate::Result<DownloadItem> {
-      match store::get(&self.0, path.clone())? {
+   pub fn get(&self, _app: AppHandle<R>, id: String) -> crate::Result<DownloadItem> {
+      match store::get(&self.app, id.clone())? {
          Some(item) => Ok(item),
          None => Ok(DownloadItem {
             url: String::new(),
-            path,
+            path: id,
             progress: 0.0,
             status: DownloadStatus::Pending,
+            ..Default::default()
          }),
       }
    }
@@ -100,6 +206,9 @@ impl<R: Runtime> Download<R> {
    /// - `app` - The application handle.
    /// - `path` - The download path.
    /// - `url` - The download URL for the resource.
+   /// - `checksum` - The expected checksum, verified before the download is completed.
+   /// - `request` - The request sent to download the resource, replayed every time the download is resumed.
+   /// - `scope` - The URLs granted to the calling window through the capability system, if called from a window.
    ///
    /// # Returns
    /// The download operation.
@@ -108,6 +217,9 @@ impl<R: Runtime> Download<R> {
       app: AppHandle<R>,
       path: String,
       url: String,
+      checksum: Option<Checksum>,
+      request: Option<DownloadRequest>,
+      scope: Option<CapabilityScope>,
    ) -> crate::Result<DownloadActionResponse> {
       // Check if item already exists
       if let Some(existing) = store::get(&app, path.clone())? {
@@ -117,16 +229,64 @@ impl<R: Runtime> Download<R> {
          ));
       }
 
-      let item = store::create(
+      self.check_destination(&path)?;
+      let parsed_url = Url::parse(&url).map_err(|_| Error::UrlNotAllowed(url.clone()))?;
+      self.url_filter.check(&parsed_url)?;
+      if let Some(scope) = &scope {
+         scope.check(parsed_url.as_str())?;
+      }
+      if let Some(checksum) = &checksum {
+         checksum::validate(checksum)?;
+      }
+
+      // Separate the sensitive header values, so they are not written to the store.
+      let mut request = request;
+      let mut sensitive_headers = HashMap::new();
+      if let Some(request) = request.as_mut() {
+         request.headers.retain(|name, value| {
+            let sensitive = request
+               .sensitive_headers
+               .iter()
+               .any(|sensitive| sensitive.eq_ignore_ascii_case(name));
+            if sensitive {
+               sensitive_headers.insert(name.clone(), value.clone());
+            }
+            !sensitive
+         });
+      }
+
+      let item = DownloadItem {
+         id: store::new_id(),
+         url,
+         path,
+         progress: 0.0,
+         status: DownloadStatus::Idle,
+         checksum,
+         request,
+         scope,
+         ..Default::default()
+      };
+
+      // Ensure the request is valid.
+      Download::<R>::request(&self.client, &item, Some(&sensitive_headers))?
+         .build()
+         .map_err(|e| Error::InvalidRequest(e.to_string()))?;
+
+      let item = store::create(&app, item)?;
+      if !sensitive_headers.is_empty() {
+         self
+            .sensitive_headers
+            .lock()
+            .unwrap()
+            .insert(item.id.clone(), sensitive_headers);
+      }
+
+      Download::emit(
          &app,
-         DownloadItem {
-            url,
-            path,
-            progress: 0.0,
-            status: DownloadStatus::Idle,
+         DownloadEvent::Created {
+            download: item.clone(),
          },
-      )?;
-
+      );
       Ok(DownloadActionResponse::new(item))
    }
 
@@ -135,24 +295,15 @@ impl<R: Runtime> Download<R> {
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID or path.
    ///
    /// # Returns
    /// The download operation.
-   pub fn start(&self, app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
-      let item = store::get(&app, path.clone())?.ok_or(Error::NotFound(path))?;
+   pub fn start(&self, app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
       match item.status {
          // Allow download to be started when idle.
-         DownloadStatus::Idle => {
-            let item_started = item.with_status(DownloadStatus::InProgress);
-            tokio::spawn(async move {
-               Download::download(&app, item_started).await.unwrap();
-            });
-
-            Ok(DownloadActionResponse::new(
-               item.with_status(DownloadStatus::InProgress),
-            ))
-         }
+         DownloadStatus::Idle => Ok(DownloadActionResponse::new(Download::enqueue(&app, item)?)),
 
          // Return current state if in any other state.
          _ => Ok(DownloadActionResponse::with_expected_status(
@@ -162,28 +313,40 @@ impl<R: Runtime> Download<R> {
       }
    }
 
+   ///
+   /// Starts a download operation, or resumes it if it is paused or failed, returning a handle which can be awaited
+   /// until the download has finished. The download is cancelled if the handle is dropped before then, unless the
+   /// handle is detached.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   ///
+   /// # Returns
+   /// The handle to the download.
+   pub fn start_with_handle(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+   ) -> crate::Result<DownloadHandle<R>> {
+      DownloadHandle::start(app, id)
+   }
+
    ///
    /// Resumes a download operation.
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID or path.
    ///
    /// # Returns
    /// The download operation.
-   pub fn resume(&self, app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
-      let item = store::get(&app, path.clone())?.ok_or(Error::NotFound(path))?;
+   pub fn resume(&self, app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
       match item.status {
-         // Allow download to be resumed when paused.
-         DownloadStatus::Paused => {
-            let item_resumed = item.with_status(DownloadStatus::InProgress);
-            tokio::spawn(async move {
-               Download::download(&app, item_resumed).await.unwrap();
-            });
-
-            Ok(DownloadActionResponse::new(
-               item.with_status(DownloadStatus::InProgress),
-            ))
+         // Allow download to be resumed when paused or failed.
+         DownloadStatus::Paused | DownloadStatus::Failed => {
+            Ok(DownloadActionResponse::new(Download::enqueue(&app, item)?))
          }
 
          // Return current state if in any other state.
@@ -194,22 +357,67 @@ impl<R: Runtime> Download<R> {
       }
    }
 
+   ///
+   /// Ensures the URL of a download is allowed by the capability scope of the window which starts or resumes it.
+   /// Downloads without a recorded scope, such as those created in Rust, adopt the scope of the window so it also
+   /// applies to redirects and refreshed URLs.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `scope` - The URLs granted to the calling window through the capability system.
+   pub(crate) fn check_scope(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      scope: CapabilityScope,
+   ) -> crate::Result<()> {
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
+      scope.check(&item.url)?;
+
+      // Only record the scope while the download is not started, as the scope applies from when it is started.
+      if item.scope.is_none()
+         && matches!(
+            item.status,
+            DownloadStatus::Idle | DownloadStatus::Paused | DownloadStatus::Failed
+         )
+      {
+         store::modify(&app, item.id, |current| {
+            if current.url == item.url && current.scope.is_none() {
+               current.scope = Some(scope);
+            }
+            Ok(())
+         })?;
+      }
+
+      Ok(())
+   }
+
    ///
    /// Pauses a download operation.
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID or path.
    ///
    /// # Returns
    /// The download operation.
-   pub fn pause(&self, app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
-      let item = store::get(&app, path.clone())?.ok_or(Error::NotFound(path))?;
+   pub fn pause(&self, app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
+      // Lock the store until the download is paused, so its task cannot record its progress in the meantime.
+      let lock = store::lock();
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
       match item.status {
-         // Allow download to be paused when in progress.
-         DownloadStatus::InProgress => {
-            store::update(&app, item.with_status(DownloadStatus::Paused)).unwrap();
-            Download::emit_changed(&app, item.with_status(DownloadStatus::Paused));
+         // Allow download to be paused when queued or in progress.
+         DownloadStatus::Queued | DownloadStatus::InProgress => {
+            store::update(&app, item.with_status(DownloadStatus::Paused))?;
+            drop(lock);
+            self.abort(&item.id);
+            Download::emit(
+               &app,
+               DownloadEvent::Paused {
+                  download: item.with_status(DownloadStatus::Paused),
+               },
+            );
             Ok(DownloadActionResponse::new(
                item.with_status(DownloadStatus::Paused),
             ))
@@ -228,17 +436,26 @@ impl<R: Runtime> Download<R> {
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID or path.
    ///
    /// # Returns
    /// The download operation.
-   pub fn cancel(&self, app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
-      let item = store::get(&app, path.clone())?.ok_or(Error::NotFound(path))?;
+   pub fn cancel(&self, app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
+      // Lock the store until the download is removed, so its task cannot record its progress in the meantime.
+      let lock = store::lock();
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
       match item.status {
-         // Allow download to be cancelled when created, in progress or paused.
-         DownloadStatus::Idle | DownloadStatus::InProgress | DownloadStatus::Paused => {
-            store::delete(&app, item.path.clone()).unwrap();
-            let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
+         // Allow download to be cancelled when created, queued, in progress, paused or failed.
+         DownloadStatus::Idle
+         | DownloadStatus::Queued
+         | DownloadStatus::InProgress
+         | DownloadStatus::Paused
+         | DownloadStatus::Failed => {
+            store::delete(&app, item.id.clone())?;
+            drop(lock);
+            self.abort(&item.id);
+            self.sensitive_headers.lock().unwrap().remove(&item.id);
+            let temp_path = self.temp_path(&item.path);
             if fs::remove_file(&temp_path).is_err() {
                println!(
                   "[{}] File was not found or could not be deleted",
@@ -246,7 +463,20 @@ impl<R: Runtime> Download<R> {
                );
             }
 
-            Download::emit_changed(&app, item.with_status(DownloadStatus::Cancelled));
+            let item_cancelled = item.with_status(DownloadStatus::Cancelled);
+            Download::record_history(&app, &item_cancelled, item.downloaded_bytes);
+            Download::emit(
+               &app,
+               DownloadEvent::Cancelled {
+                  download: item_cancelled.clone(),
+               },
+            );
+            Download::emit(
+               &app,
+               DownloadEvent::Removed {
+                  download: item_cancelled,
+               },
+            );
             Ok(DownloadActionResponse::new(
                item.with_status(DownloadStatus::Cancelled),
             ))
@@ -260,140 +490,2062 @@ impl<R: Runtime> Download<R> {
       }
    }
 
-   async fn download(app: &AppHandle<R>, item: DownloadItem) -> crate::Result<()> {
-      let client = reqwest::Client::new();
-      let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
+   ///
+   /// Subscribes a channel to the updates of a download, until the download is completed or cancelled. Unlike the
+   /// `tauri-plugin-download:changed` event, updates are only sent to the subscriber.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `channel` - The channel which receives the updated download.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn subscribe(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      channel: Channel<DownloadItem>,
+   ) -> crate::Result<DownloadItem> {
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
+      self
+         .channels
+         .lock()
+         .unwrap()
+         .entry(item.id.clone())
+         .or_default()
+         .push(channel);
 
-      // Check the size of the already downloaded part, if any.
-      let downloaded_size = if std::path::Path::new(&temp_path).exists() {
-         fs::metadata(&temp_path)
-            .map(|metadata| metadata.len())
-            .unwrap_or(0)
-      } else {
-         0
+      Ok(item)
+   }
+
+   ///
+   /// Unsubscribes a channel from the updates of a download.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `channel_id` - The ID of the channel.
+   pub fn unsubscribe(&self, app: AppHandle<R>, id: String, channel_id: u32) -> crate::Result<()> {
+      // The download may have been removed in the meantime, in which case its ID is unchanged.
+      let id = Download::resolve_id(&app, id)?;
+      let mut channels = self.channels.lock().unwrap();
+      if let Some(subscribed) = channels.get_mut(&id) {
+         subscribed.retain(|channel| channel.id() != channel_id);
+         if subscribed.is_empty() {
+            channels.remove(&id);
+         }
+      }
+
+      Ok(())
+   }
+
+   ///
+   /// Registers a callback which is invoked with each event in the lifecycle of every download, such as its
+   /// completion. The callback is invoked on the task which emits the event, so it should not block.
+   ///
+   /// # Arguments
+   /// - `callback` - The callback.
+   ///
+   /// # Returns
+   /// The ID of the callback, used to unregister it.
+   pub fn on_event(
+      &self,
+      callback: impl Fn(&DownloadEvent) + Send + Sync + 'static,
+   ) -> crate::Result<u64> {
+      let id = self.next_event_callback_id.fetch_add(1, Ordering::Relaxed);
+      self
+         .event_callbacks
+         .lock()
+         .unwrap()
+         .insert(id, Arc::new(callback));
+
+      Ok(id)
+   }
+
+   ///
+   /// Unregisters a callback registered with `on_event`.
+   ///
+   /// # Arguments
+   /// - `id` - The ID of the callback.
+   pub fn remove_event_callback(&self, id: u64) -> crate::Result<()> {
+      self.event_callbacks.lock().unwrap().remove(&id);
+      Ok(())
+   }
+
+   ///
+   /// Subscribes to the events in the lifecycle of every download. Up to 256 events are buffered; a receiver which
+   /// falls further behind skips the oldest events.
+   ///
+   /// # Returns
+   /// The receiver of the events.
+   pub fn events(&self) -> crate::Result<broadcast::Receiver<DownloadEvent>> {
+      Ok(self.events.subscribe())
+   }
+
+   ///
+   /// Sets the priority of a download operation.
+   /// Queued downloads with a higher priority are started before those with a lower priority.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `priority` - The priority.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn set_priority(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      priority: i32,
+   ) -> crate::Result<DownloadItem> {
+      let item_updated = store::modify(&app, id, |item| {
+         Download::<R>::check_unfinished(item)?;
+         item.priority = priority;
+         Ok(())
+      })?;
+      Download::emit_changed(&app, item_updated.clone());
+      Download::schedule(&app);
+
+      Ok(item_updated)
+   }
+
+   ///
+   /// Sets the rate limit of a download operation, or the total rate limit of all download operations.
+   /// The limit applies immediately, including to download operations which are in progress.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path, or `None` to set the total rate limit.
+   /// - `rate_limit` - The limit in bytes per second, or `None` to remove the limit.
+   pub fn set_rate_limit(
+      &self,
+      app: AppHandle<R>,
+      id: Option<String>,
+      rate_limit: Option<u64>,
+   ) -> crate::Result<()> {
+      let Some(id) = id else {
+         self.rate_limiter.set_rate(rate_limit);
+         return Ok(());
       };
 
-      // Set the Range header for resuming the download.
-      let mut headers = HeaderMap::new();
-      if downloaded_size > 0 {
-         headers.insert(
-            RANGE,
-            format!("bytes={}-", downloaded_size).parse().unwrap(),
-         );
+      let item_updated = store::modify(&app, id, |item| {
+         Download::<R>::check_unfinished(item)?;
+         item.rate_limit = rate_limit;
+         Ok(())
+      })?;
+      if let Some(rate_limiter) = self.rate_limiters.lock().unwrap().get(&item_updated.id) {
+         rate_limiter.set_rate(rate_limit);
       }
 
-      // Send the request.
-      let response = match client.get(&item.url).headers(headers).send().await {
-         Ok(res) => res,
-         Err(e) => {
-            return Err(Error::Http(format!("Failed to send request: {}", e)));
+      Download::emit_changed(&app, item_updated);
+      Ok(())
+   }
+
+   ///
+   /// Changes the destination of a download operation which is not in progress, moving its partial download (if any)
+   /// so the download continues from the same offset.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `path` - The new download path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn set_destination(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      path: String,
+   ) -> crate::Result<DownloadItem> {
+      // Lock the store until the download is moved, so it cannot be started in the meantime, as its task would write
+      // to the partial download.
+      let lock = store::lock();
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
+      self.check_editable(&item)?;
+      if item.path == path {
+         return Ok(item);
+      }
+
+      self.check_destination(&path)?;
+      if store::list(&app)?
+         .iter()
+         .any(|existing| existing.id != item.id && existing.path == path)
+      {
+         return Err(Error::Store(format!(
+            "Item already exists for path: {}",
+            &path
+         )));
+      }
+
+      // Move the partial download, if any.
+      let temp_path = self.temp_path(&item.path);
+      let new_temp_path = self.temp_path(&path);
+      let moved = Path::new(&temp_path).exists();
+      if moved {
+         if let Some(folder) = Path::new(&new_temp_path).parent() {
+            fs::create_dir_all(folder)
+               .map_err(|e| Error::File(format!("Failed to create folder: {}", e)))?;
          }
-      };
+         move_file(&temp_path, &new_temp_path)
+            .map_err(|e| Error::File(format!("Failed to move partial download: {}", e)))?;
+      }
 
-      // Ensure the server supports partial downloads.
-      if downloaded_size > 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-         return Err(Error::Http(
-            "Server does not support partial downloads".to_string(),
-         ));
+      let item_moved = DownloadItem { path, ..item };
+      if let Err(e) = store::update(&app, item_moved.clone()) {
+         // Restore the partial download, so the download continues where it was.
+         if moved && let Err(e) = move_file(&new_temp_path, &temp_path) {
+            eprintln!("[{}] Failed to restore partial download: {}", &temp_path, e);
+         }
+         return Err(e);
       }
+      drop(lock);
+      Download::emit_changed(&app, item_moved.clone());
 
-      // Get the total size of the file from headers (if available).
-      let total_size = response
-         .headers()
-         .get("content-length")
-         .and_then(|len| len.to_str().ok())
-         .and_then(|len| len.parse::<u64>().ok())
-         .map(|len| len + downloaded_size)
-         .unwrap_or(0);
+      Ok(item_moved)
+   }
 
-      // Ensure the output folder exists.
-      let folder = Path::new(&temp_path).parent().unwrap();
-      if !folder.exists() {
-         fs::create_dir(folder).unwrap();
+   ///
+   /// Changes the URL of a download operation which is not in progress, such as to another mirror. The partial
+   /// download (if any) is kept if the new URL serves the same resource, which is validated by its `ETag` and
+   /// length. Otherwise the download restarts from the beginning when resumed.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `url` - The new download URL for the resource.
+   /// - `scope` - The URLs granted to the calling window through the capability system, if called from a window.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub async fn set_url(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      url: String,
+      scope: Option<CapabilityScope>,
+   ) -> crate::Result<DownloadItem> {
+      let item = store::get(&app, id.clone())?.ok_or(Error::NotFound(id))?;
+      self.check_editable(&item)?;
+
+      let parsed_url = Url::parse(&url).map_err(|_| Error::UrlNotAllowed(url.clone()))?;
+      self.check_url(&item, &parsed_url)?;
+      if let Some(scope) = &scope {
+         scope.check(parsed_url.as_str())?;
       }
 
-      // Open the temp file in append mode.
-      let mut file = OpenOptions::new()
-         .create(true)
-         .append(true)
-         .open(&temp_path)
-         .map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+      // Request the headers of the resource from the new URL, to validate the partial download (if any).
+      let temp_path = self.temp_path(&item.path);
+      let mut validators = None;
+      let mut discard = false;
+      if Download::<R>::downloaded_size(&temp_path) > 0 {
+         let probe = DownloadItem {
+            url: parsed_url.to_string(),
+            request: Some(DownloadRequest {
+               method: Some("HEAD".to_string()),
+               body: None,
+               ..item.request.clone().unwrap_or_default()
+            }),
+            ..item.clone()
+         };
+         let response = Download::send(&app, &probe, HeaderMap::new())
+            .await
+            .and_then(|response| {
+               Download::check_status(&app, &response)?;
+               Ok(response)
+            })
+            .map_err(|(TransferError::Transient(e, _) | TransferError::Permanent(e))| e)?;
 
-      // Write the response body to the file in chunks.
-      let mut downloaded = downloaded_size;
-      let mut stream = response.bytes_stream();
+         let header = |name| {
+            response
+               .headers()
+               .get(name)
+               .and_then(|value| value.to_str().ok())
+               .map(str::to_string)
+         };
+         let etag = header(ETAG);
+         let total_bytes = header(CONTENT_LENGTH).and_then(|len| len.parse::<u64>().ok());
+         let same_etag = match (&item.etag, &etag) {
+            (Some(etag), Some(new_etag)) => Some(etag == new_etag),
+            _ => None,
+         };
+         let same_length = match (item.total_bytes, total_bytes) {
+            (Some(total_bytes), Some(new_total_bytes)) => Some(total_bytes == new_total_bytes),
+            _ => None,
+         };
 
-      // Throttle progress updates.
-      let mut last_emitted_progress = 0.0;
-      const PROGRESS_THRESHOLD: f64 = 1.0; // Only update if progress increases by at least 1%.
-
-      store::update(app, item.with_status(DownloadStatus::InProgress)).unwrap();
-      Download::emit_changed(app, item.with_status(DownloadStatus::InProgress));
-
-      'reader: while let Some(chunk) = stream.next().await {
-         match chunk {
-            Ok(data) => {
-               file
-                  .write_all(&data)
-                  .map_err(|e| Error::File(format!("Failed to write file: {}", e)))?;
-
-               downloaded += data.len() as u64;
-               let progress = (downloaded as f64 / total_size as f64) * 100.0;
-               if progress < 100.0 && progress - last_emitted_progress <= PROGRESS_THRESHOLD {
-                  // Ignore any progress updates below the threshold.
-                  continue;
-               }
+         // The resource is the same if nothing known about it differs, and at least its ETag or length matches.
+         if same_etag != Some(false)
+            && same_length != Some(false)
+            && (same_etag.is_some() || same_length.is_some())
+         {
+            validators = Some((etag, header(LAST_MODIFIED)));
+         } else {
+            discard = true;
+         }
+      }
 
-               last_emitted_progress = progress;
-               if let Ok(Some(item)) = store::get(app, item.path.clone()) {
-                  match item.status {
-                     // Download is in progress.
-                     DownloadStatus::InProgress => {
-                        if progress < 100.0 {
-                           // Download is not yet complete.
-                           // Update item in store and emit change event.
-                           store::update(app, item.with_progress(progress)).unwrap();
-                           Download::emit_changed(app, item.with_progress(progress));
-                        } else if progress == 100.0 {
-                           // Download has completed.
-                           // Remove item from store, rename temp file to final path and emit change event.
-                           store::delete(app, item.path.clone()).unwrap();
-
-                           let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
-                           fs::rename(&temp_path, &item.path)?;
-                           Download::emit_changed(app, item.with_status(DownloadStatus::Completed));
-                        }
-                     }
-                     // Download was paused.
-                     DownloadStatus::Paused => {
-                        break 'reader;
-                     }
-                     _ => (),
-                  }
-               } else {
-                  // Download item was not found i.e. removed.
-                  break 'reader;
-               }
-            }
-            Err(e) => {
-               // Download error occured.
-               // Remove item from store and partial download.
-               store::delete(app, item.path.clone()).unwrap();
-               let temp_path = format!("{}{}", item.path, DOWNLOAD_SUFFIX);
-               if std::path::Path::new(&temp_path).exists() {
-                  fs::remove_file(&temp_path)?;
-               }
+      // Ensure the download was not started, changed or removed while validating the new URL, before discarding its
+      // partial download. The store is locked until the download is updated, so it cannot be started in the meantime.
+      let lock = store::lock();
+      let current = store::get(&app, item.id.clone())?.ok_or(Error::NotFound(item.id.clone()))?;
+      self.check_editable(&current)?;
+      if current.url != item.url || current.path != item.path {
+         return Err(Error::InvalidState);
+      }
 
-               return Err(Error::Http(format!("Failed to download: {}", e)));
-            }
+      let mut item_updated = DownloadItem {
+         url: parsed_url.to_string(),
+         ..current
+      };
+      if let Some((etag, last_modified)) = validators {
+         item_updated.etag = etag.or(item_updated.etag);
+         item_updated.last_modified = last_modified.or(item_updated.last_modified);
+      }
+      if discard {
+         fs::remove_file(&temp_path).map_err(Error::from)?;
+         item_updated = DownloadItem {
+            progress: 0.0,
+            downloaded_bytes: 0,
+            total_bytes: None,
+            segments: Vec::new(),
+            etag: None,
+            last_modified: None,
+            resumable: None,
+            ..item_updated
+         };
+      }
+
+      store::update(&app, item_updated.clone())?;
+      drop(lock);
+      Download::emit_changed(&app, item_updated.clone());
+
+      Ok(item_updated)
+   }
+
+   ///
+   /// Ensures a download operation can be changed, i.e. it is not in progress and its previous task (if any) has
+   /// finished.
+   ///
+   fn check_editable(&self, item: &DownloadItem) -> crate::Result<()> {
+      match item.status {
+         DownloadStatus::Idle
+         | DownloadStatus::Queued
+         | DownloadStatus::Paused
+         | DownloadStatus::Failed
+            if !self.tasks.lock().unwrap().contains_key(&item.id) =>
+         {
+            Ok(())
+         }
+         _ => Err(Error::InvalidState),
+      }
+   }
+
+   ///
+   /// Gets the finished download operations recorded in the download history, most recently finished first.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `query` - Filters and paginates the download history.
+   ///
+   /// # Returns
+   /// The page of the download history.
+   pub fn history(&self, app: AppHandle<R>, query: HistoryQuery) -> crate::Result<HistoryPage> {
+      history::list(&app, query)
+   }
+
+   ///
+   /// Removes all download operations from the download history.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   pub fn clear_history(&self, app: AppHandle<R>) -> crate::Result<()> {
+      history::clear(&app)
+   }
+
+   ///
+   /// Removes a download operation from the download history.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID.
+   pub fn remove_from_history(&self, app: AppHandle<R>, id: String) -> crate::Result<()> {
+      history::remove(&app, id)
+   }
+
+   ///
+   /// Registers or unregisters a hook which refreshes the request of a download in JavaScript. Each webview can
+   /// register several hooks, which are all unregistered when the webview navigates, reloads or is closed.
+   ///
+   /// # Arguments
+   /// - `label` - The label of the webview which registered the hook.
+   /// - `enabled` - Whether the hook is registered.
+   pub fn set_refresh_listener(&self, label: String, enabled: bool) -> crate::Result<()> {
+      let mut listeners = self.refresh_listeners.lock().unwrap();
+      if enabled {
+         *listeners.entry(label).or_default() += 1;
+      } else if let Some(count) = listeners.get_mut(&label) {
+         *count -= 1;
+         if *count == 0 {
+            listeners.remove(&label);
          }
       }
 
       Ok(())
    }
 
-   fn emit_changed(app: &AppHandle<R>, item: DownloadItem) {
-      app.emit("tauri-plugin-download:changed", &item).unwrap();
-      println!("[{}] {} - {:.0}%", item.path, item.status, item.progress);
+   ///
+   /// Unregisters the hooks which refresh the request of a download registered by a webview, such as when it
+   /// navigates, reloads or is closed.
+   ///
+   /// # Arguments
+   /// - `label` - The label of the webview.
+   pub(crate) fn remove_refresh_listeners(&self, label: &str) {
+      self.refresh_listeners.lock().unwrap().remove(label);
+   }
+
+   ///
+   /// Whether a hook which refreshes the request of a download is registered in JavaScript by any webview.
+   ///
+   fn has_refresh_listener(&self) -> bool {
+      !self.refresh_listeners.lock().unwrap().is_empty()
+   }
+
+   ///
+   /// Answers a refresh request from JavaScript.
+   ///
+   /// # Arguments
+   /// - `request_id` - The ID of the refresh request.
+   /// - `refresh` - The refreshed request, or `None` to leave the request unchanged.
+   pub fn refresh(&self, request_id: u64, refresh: Option<DownloadRefresh>) -> crate::Result<()> {
+      let sender = self
+         .refresh_requests
+         .lock()
+         .unwrap()
+         .remove(&request_id)
+         .ok_or(Error::NotFound(format!("Refresh request {}", request_id)))?;
+
+      // The request may have timed out in the meantime.
+      let _ = sender.send(refresh);
+      Ok(())
+   }
+
+   ///
+   /// Moves a queued download operation to the front of the queue, so it is the next to be started.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn move_to_front(&self, app: AppHandle<R>, id: String) -> crate::Result<DownloadItem> {
+      let id = Download::resolve_id(&app, id)?;
+      let queue = Download::queue(&app)?;
+      match queue.first() {
+         Some(first) if first.id != id => self.move_before(app, id, first.id.clone()),
+         _ => self.move_before(app, id.clone(), id),
+      }
+   }
+
+   ///
+   /// Moves a queued download operation before another queued download operation.
+   /// The download takes the priority of the download it is moved before.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   /// - `before_id` - The ID or path of the download to move before.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn move_before(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      before_id: String,
+   ) -> crate::Result<DownloadItem> {
+      let id = Download::resolve_id(&app, id)?;
+      let before_id = Download::resolve_id(&app, before_id)?;
+
+      // Lock the store until the queue is renumbered, so only downloads which are still queued are changed.
+      let lock = store::lock();
+      let mut queue = Download::queue(&app)?;
+      Download::<R>::reorder(&mut queue, &id, &before_id)
+         .map_err(|missing| Download::<R>::not_queued(&app, missing))?;
+
+      // Renumber the queue positions to reflect the new order, only changing the priority and position.
+      let mut moved = Vec::new();
+      for (position, queued) in queue.into_iter().enumerate() {
+         if queued.queue_position != Some(position as i64) || queued.id == id {
+            let item_moved = queued.with_queue_position(position as i64);
+            store::update(&app, item_moved.clone())?;
+            moved.push(item_moved);
+         }
+      }
+      drop(lock);
+
+      for item_moved in moved {
+         Download::emit_changed(&app, item_moved);
+      }
+      Download::schedule(&app);
+      store::get(&app, id.clone())?.ok_or(Error::NotFound(id))
+   }
+
+   ///
+   /// Gets the queued download operations, in the order they will be started.
+   ///
+   fn queue(app: &AppHandle<R>) -> crate::Result<Vec<DownloadItem>> {
+      let mut queued: Vec<DownloadItem> = store::list(app)?
+         .into_iter()
+         .filter(|item| item.status == DownloadStatus::Queued)
+         .collect();
+
+      Download::<R>::sort_queue(&mut queued);
+      Ok(queued)
+   }
+
+   ///
+   /// Sorts queued download operations in the order they will be started: by priority (highest first), then by queue
+   /// position, then by ID.
+   ///
+   fn sort_queue(queued: &mut [DownloadItem]) {
+      queued.sort_by(|a, b| {
+         (b.priority, a.queue_position, &a.id).cmp(&(a.priority, b.queue_position, &b.id))
+      });
+   }
+
+   ///
+   /// Moves a download before another download in the queue, taking its priority. Moving a download before itself
+   /// leaves the queue unchanged.
+   ///
+   /// # Arguments
+   /// - `queue` - The queued download operations, in the order they will be started.
+   /// - `id` - The ID of the download to move.
+   /// - `before_id` - The ID of the download to move before.
+   ///
+   /// # Returns
+   /// The ID of the download which is not in the queue, if any.
+   fn reorder(queue: &mut Vec<DownloadItem>, id: &str, before_id: &str) -> Result<(), String> {
+      let index = queue
+         .iter()
+         .position(|item| item.id == id)
+         .ok_or_else(|| id.to_string())?;
+      if before_id == id {
+         return Ok(());
+      }
+
+      let mut item = queue.remove(index);
+      let Some(before_index) = queue.iter().position(|item| item.id == before_id) else {
+         queue.insert(index, item);
+         return Err(before_id.to_string());
+      };
+      item.priority = queue[before_index].priority;
+      queue.insert(before_index, item);
+
+      Ok(())
+   }
+
+   ///
+   /// Ensures a download operation has not finished, so it can still be changed.
+   ///
+   fn check_unfinished(item: &DownloadItem) -> crate::Result<()> {
+      match item.status {
+         DownloadStatus::Idle
+         | DownloadStatus::Queued
+         | DownloadStatus::InProgress
+         | DownloadStatus::Paused
+         | DownloadStatus::Failed => Ok(()),
+         _ => Err(Error::InvalidState),
+      }
+   }
+
+   ///
+   /// Gets the error for a download operation which was expected to be queued.
+   ///
+   fn not_queued(app: &AppHandle<R>, id: String) -> Error {
+      match store::get(app, id.clone()) {
+         Ok(Some(_)) => Error::InvalidState,
+         _ => Error::NotFound(id),
+      }
+   }
+
+   ///
+   /// Gets the ID of a download operation from its ID or path. Unknown IDs are returned unchanged.
+   ///
+   fn resolve_id(app: &AppHandle<R>, id: String) -> crate::Result<String> {
+      Ok(store::get(app, id.clone())?.map_or(id, |item| item.id))
+   }
+
+   ///
+   /// Adds a download to the end of the queue and starts queued downloads, up to the concurrency limit.
+   ///
+   /// # Returns
+   /// The download operation, which is in progress if it was started immediately or queued otherwise.
+   fn enqueue(app: &AppHandle<R>, item: DownloadItem) -> crate::Result<DownloadItem> {
+      // Lock the store until the download is queued, so it is not queued if it changed in the meantime.
+      let lock = store::lock();
+      let current = store::get(app, item.id.clone())?.ok_or(Error::NotFound(item.id.clone()))?;
+      if current.status != item.status {
+         return Err(Error::InvalidState);
+      }
+
+      let position = store::list(app)?
+         .iter()
+         .filter_map(|item| item.queue_position)
+         .max()
+         .map_or(0, |position| position + 1);
+
+      let item_queued = current.with_queue_position(position);
+      store::update(app, item_queued.clone())?;
+      drop(lock);
+      Download::emit_changed(app, item_queued.clone());
+      Download::schedule(app);
+
+      Ok(store::get(app, item.id.clone())?.unwrap_or(item_queued))
+   }
+
+   ///
+   /// Starts queued downloads in priority and queue order, up to the concurrency limit.
+   ///
+   fn schedule(app: &AppHandle<R>) {
+      let download = app.download();
+      let queued = match Download::queue(app) {
+         Ok(queued) => queued,
+         Err(e) => {
+            eprintln!("Failed to load download store: {}", e);
+            return;
+         }
+      };
+
+      for item in queued {
+         // Never start a second task for a download whose previous task is still finishing.
+         if download.tasks.lock().unwrap().contains_key(&item.id)
+            || !download.scheduler.try_acquire(&download.config, &item)
+         {
+            continue;
+         }
+
+         // Start the download as it is now, unless it was paused or removed since the queue was read. The store is
+         // locked until the download is started, so it cannot be paused or removed in the meantime.
+         let lock = store::lock();
+         let item = match store::get(app, item.id.clone()) {
+            Ok(Some(current)) if current.status == DownloadStatus::Queued => current,
+            Ok(_) => {
+               download.scheduler.release(&item.id);
+               continue;
+            }
+            Err(e) => {
+               eprintln!("[{}] Failed to load download: {}", &item.path, e);
+               download.scheduler.release(&item.id);
+               continue;
+            }
+         };
+         let item_started = DownloadItem {
+            started_at: item.started_at.or_else(|| Some(now_millis())),
+            ..item.with_status(DownloadStatus::InProgress)
+         };
+         if let Err(e) = store::update(app, item_started.clone()) {
+            eprintln!("[{}] Failed to update download status: {}", &item.path, e);
+            download.scheduler.release(&item.id);
+            continue;
+         }
+         drop(lock);
+
+         let event = if item_started.downloaded_bytes > 0 {
+            DownloadEvent::Resumed {
+               download: item_started.clone(),
+            }
+         } else {
+            DownloadEvent::Started {
+               download: item_started.clone(),
+            }
+         };
+         Download::emit(app, event);
+         Download::spawn(app.clone(), item_started);
+      }
+   }
+
+   ///
+   /// Spawns a task to perform the download, marking the download as failed if an error occurs.
+   /// The task is registered so it can be aborted when the download is paused or cancelled. Once the task finishes,
+   /// its slot is released and the next queued download is started.
+   ///
+   fn spawn(app: AppHandle<R>, item: DownloadItem) {
+      app.download()
+         .rate_limiters
+         .lock()
+         .unwrap()
+         .insert(item.id.clone(), Arc::new(RateLimiter::new(item.rate_limit)));
+
+      let (abort_handle, registration) = AbortHandle::new_pair();
+      app.download()
+         .tasks
+         .lock()
+         .unwrap()
+         .insert(item.id.clone(), abort_handle);
+
+      tauri::async_runtime::spawn(async move {
+         let download = Abortable::new(Download::download(&app, item.clone()), registration);
+         // The task is aborted when the download is paused or cancelled, which is not a failure.
+         if let Ok(Err(e)) = download.await {
+            Download::fail(&app, &item.id, e);
+         }
+
+         app.download().tasks.lock().unwrap().remove(&item.id);
+         app.download()
+            .rate_limiters
+            .lock()
+            .unwrap()
+            .remove(&item.id);
+         app.download().scheduler.release(&item.id);
+         Download::schedule(&app);
+      });
+   }
+
+   ///
+   /// Aborts the running task of a download, if any, which immediately closes its connections. The task remains
+   /// registered until it has released its slot.
+   ///
+   fn abort(&self, id: &str) {
+      if let Some(task) = self.tasks.lock().unwrap().get(id) {
+         task.abort();
+      }
+   }
+
+   ///
+   /// Marks a download as failed, persisting the error details to the store.
+   /// The partial download is kept so the download can later be resumed.
+   ///
+   fn fail(app: &AppHandle<R>, id: &str, error: Error) {
+      eprintln!("[{}] Download failed: {}", id, error);
+
+      // Only fail the download if it is still in progress i.e. not paused or cancelled in the meantime.
+      let error = DownloadError::from(&error);
+      match Download::update_in_progress(app, id, |item| item.with_error(error.clone())) {
+         Ok(Some(item_failed)) => Download::emit(
+            app,
+            DownloadEvent::Failed {
+               download: item_failed,
+               error,
+            },
+         ),
+         Ok(None) => (),
+         Err(e) => eprintln!("[{}] Failed to update download status: {}", id, e),
+      }
+   }
+
+   ///
+   /// Performs the download, retrying transient errors according to the retry policy.
+   ///
+   async fn download(app: &AppHandle<R>, item: DownloadItem) -> crate::Result<()> {
+      let policy = &app.download().config.retry;
+      let mut attempt = 0;
+      let mut refreshed = false;
+
+      // Refresh the request if the values of its sensitive headers were lost, such as when the application restarted,
+      // or before resuming a partial download, as it may have expired.
+      if !Download::missing_sensitive_headers(app, &item.id)?.is_empty() {
+         Download::request_refresh(app, &item.id, RefreshReason::MissingHeaders, None).await?;
+      } else if Download::downloaded_bytes(app, &item.id) > 0 {
+         Download::request_refresh(app, &item.id, RefreshReason::Resume, None).await?;
+      }
+
+      // Fail rather than sending the request without its sensitive headers, which the server would likely reject.
+      let missing = Download::missing_sensitive_headers(app, &item.id)?;
+      if !missing.is_empty() {
+         return Err(Error::SensitiveHeadersMissing(missing.join(", ")));
+      }
+
+      loop {
+         let downloaded_bytes = Download::downloaded_bytes(app, &item.id);
+         let (error, retry_after) = match Download::transfer(app, &item.id).await {
+            Ok(()) => return Ok(()),
+            // Refresh the request once if the server rejected it, then try again.
+            Err(TransferError::Permanent(Error::HttpStatus(status @ (401 | 403))))
+               if !refreshed =>
+            {
+               refreshed = true;
+               let reason = RefreshReason::Unauthorized;
+               if Download::request_refresh(app, &item.id, reason, Some(status)).await? {
+                  continue;
+               }
+               return Err(Error::HttpStatus(status));
+            }
+            Err(TransferError::Permanent(e)) => return Err(e),
+            Err(TransferError::Transient(e, retry_after)) => (e, retry_after),
+         };
+
+         // Reset the retry attempts if the transfer made progress before failing.
+         if Download::downloaded_bytes(app, &item.id) > downloaded_bytes {
+            attempt = 0;
+            refreshed = false;
+         }
+
+         attempt += 1;
+         if attempt > policy.max_attempts {
+            return Err(error);
+         }
+
+         let delay = retry_after
+            .unwrap_or_else(|| policy.delay(attempt))
+            .min(Duration::from_millis(policy.max_delay_ms));
+
+         // Record the pending retry, unless the download was paused or removed in the meantime.
+         let retry = DownloadRetry {
+            attempt,
+            max_attempts: policy.max_attempts,
+            next_retry_at: now_millis() + delay.as_millis() as u64,
+         };
+         let Some(item_retrying) = Download::update_in_progress(app, &item.id, |current| {
+            current.with_retry(retry.clone())
+         })?
+         else {
+            return Ok(());
+         };
+         Download::emit(
+            app,
+            DownloadEvent::Retrying {
+               download: item_retrying,
+               retry,
+               error: DownloadError::from(&error),
+            },
+         );
+
+         eprintln!(
+            "[{}] Retrying in {:.1}s ({}/{}): {}",
+            item.path,
+            delay.as_secs_f64(),
+            attempt,
+            policy.max_attempts,
+            error
+         );
+         tokio::time::sleep(delay).await;
+
+         // Ensure the download was not paused or removed while waiting.
+         if Download::current_in_progress(app, &item.id)?.is_none() {
+            return Ok(());
+         }
+      }
+   }
+
+   ///
+   /// Requests a refreshed request for a download from the hook registered in Rust or JavaScript (if any), then
+   /// records it so the download continues from its current offset with the new URL and/or headers.
+   ///
+   /// # Returns
+   /// Whether the request was refreshed.
+   async fn request_refresh(
+      app: &AppHandle<R>,
+      id: &str,
+      reason: RefreshReason,
+      http_status: Option<u16>,
+   ) -> crate::Result<bool> {
+      let Some(item) = store::get(app, id.to_string())? else {
+         return Ok(false);
+      };
+
+      let download = app.download();
+      let request = RefreshRequest {
+         request_id: download.next_refresh_id.fetch_add(1, Ordering::Relaxed),
+         reason,
+         http_status,
+         download: item,
+      };
+
+      let refresh = if let Some(hook) = download.refresh_hook.clone() {
+         hook(app.clone(), request).await?
+      } else if download.has_refresh_listener() {
+         // Send the request to JavaScript and wait for the answer.
+         let request_id = request.request_id;
+         let (sender, receiver) = oneshot::channel();
+         download
+            .refresh_requests
+            .lock()
+            .unwrap()
+            .insert(request_id, sender);
+         app.emit("tauri-plugin-download:refresh", &request).unwrap();
+
+         let refresh = tokio::time::timeout(REFRESH_TIMEOUT, receiver).await;
+         download
+            .refresh_requests
+            .lock()
+            .unwrap()
+            .remove(&request_id);
+         match refresh {
+            Ok(Ok(refresh)) => refresh,
+            _ => {
+               eprintln!("[{}] Timed out waiting for refresh", id);
+               None
+            }
+         }
+      } else {
+         None
+      };
+
+      let Some(refresh) = refresh else {
+         return Ok(false);
+      };
+
+      // Record the refreshed request, unless the download was removed in the meantime.
+      let record = |item: &mut DownloadItem| {
+         if let Some(url) = refresh.url {
+            item.url = url;
+         }
+
+         let request = item.request.get_or_insert_with(DownloadRequest::default);
+         let mut sensitive_headers = download.sensitive_headers.lock().unwrap();
+         for (name, value) in refresh.headers {
+            let sensitive = refresh
+               .sensitive_headers
+               .iter()
+               .any(|sensitive| sensitive.eq_ignore_ascii_case(&name));
+
+            // Replace any existing value of the header, whether sensitive or not.
+            request
+               .headers
+               .retain(|existing, _| !existing.eq_ignore_ascii_case(&name));
+            if let Some(values) = sensitive_headers.get_mut(id) {
+               values.retain(|existing, _| !existing.eq_ignore_ascii_case(&name));
+            }
+
+            if sensitive {
+               if !request
+                  .sensitive_headers
+                  .iter()
+                  .any(|existing| existing.eq_ignore_ascii_case(&name))
+               {
+                  request.sensitive_headers.push(name.clone());
+               }
+               sensitive_headers
+                  .entry(id.to_string())
+                  .or_default()
+                  .insert(name, value);
+            } else {
+               request.headers.insert(name, value);
+            }
+         }
+
+         Ok(())
+      };
+      match store::modify(app, id.to_string(), record) {
+         Ok(_) => Ok(true),
+         Err(Error::NotFound(_)) => Ok(false),
+         Err(e) => Err(e),
+      }
+   }
+
+   ///
+   /// Performs a single transfer attempt, resuming from the partial download if one exists.
+   ///
+   async fn transfer(app: &AppHandle<R>, id: &str) -> Result<(), TransferError> {
+      let Some(item) = Download::current_in_progress(app, id)? else {
+         return Ok(());
+      };
+
+      // Ensure the destination is still allowed, as symlinks may have changed since the download was created.
+      app.download().check_destination(&item.path)?;
+
+      // Resume each segment of a segmented download.
+      if !item.segments.is_empty() {
+         return Download::transfer_segments(app, item).await;
+      }
+
+      let temp_path = app.download().temp_path(&item.path);
+
+      // Check the size of the already downloaded part, if any.
+      let mut downloaded_size = Download::<R>::downloaded_size(&temp_path);
+
+      // Complete the download if all bytes were already downloaded, such as when it previously could not be moved.
+      if downloaded_size > 0 && item.total_bytes == Some(downloaded_size) {
+         let hasher = Download::<R>::hash_file(&item, &temp_path).await?;
+         return Download::complete(app, &item.id, hasher, downloaded_size);
+      }
+
+      // Set the Range header for resuming the download.
+      let mut headers = HeaderMap::new();
+      if downloaded_size > 0 {
+         headers.insert(
+            RANGE,
+            format!("bytes={}-", downloaded_size).parse().unwrap(),
+         );
+         if let Some(value) = Download::<R>::if_range(&item) {
+            headers.insert(IF_RANGE, value);
+         }
+      }
+
+      // Send the request.
+      let mut response = Download::send(app, &item, headers).await?;
+
+      // Handle the partial download starting at or past the end of the resource. The download is complete if the
+      // partial download is the size of the resource, otherwise the resource has changed so the download is restarted.
+      if downloaded_size > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+         let (_, total_size) = Download::<R>::content_range(response.headers()).unwrap_or_default();
+         if total_size == Some(downloaded_size) {
+            let hasher = Download::<R>::hash_file(&item, &temp_path).await?;
+            return Download::complete(app, &item.id, hasher, downloaded_size);
+         }
+
+         downloaded_size = 0;
+         response = Download::send(app, &item, HeaderMap::new()).await?;
+      }
+
+      // Ensure the server responded successfully.
+      Download::check_status(app, &response)?;
+
+      // Check whether the server supports partial downloads.
+      let status = response.status();
+      let resumable = status == reqwest::StatusCode::PARTIAL_CONTENT
+         || response
+            .headers()
+            .get(ACCEPT_RANGES)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.eq_ignore_ascii_case("bytes"));
+
+      // Handle the server sending the full resource when resuming.
+      let range_policy = &app.download().config.range_policy;
+      if downloaded_size > 0
+         && let Some(policy) = Download::<R>::resume_policy(status, resumable, range_policy)
+      {
+         match policy {
+            RangePolicy::Restart => downloaded_size = 0,
+            RangePolicy::Fail => return Err(Error::RangeNotSupported.into()),
+            RangePolicy::Pause => {
+               let item_paused =
+                  Download::update_in_progress(app, &item.id, |current| DownloadItem {
+                     resumable: Some(false),
+                     ..current.with_status(DownloadStatus::Paused)
+                  })?;
+               if let Some(item_paused) = item_paused {
+                  Download::emit(
+                     app,
+                     DownloadEvent::Paused {
+                        download: item_paused,
+                     },
+                  );
+               }
+               return Ok(());
+            }
+         }
+      }
+
+      // Get the total size of the file from headers (if available), keeping the known size when resuming.
+      let total_size = Download::<R>::total_size(response.headers(), downloaded_size)
+         .or(item.total_bytes.filter(|_| downloaded_size > 0));
+
+      // Record the size of the download, whether the download is resumable and the validators of the resource, used
+      // to validate the partial download when resuming.
+      let header = |name| {
+         response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+      };
+      let record = |current: DownloadItem| {
+         let mut item_updated = DownloadItem {
+            resumable: Some(resumable),
+            ..current.with_progress(downloaded_size, total_size, None)
+         };
+         if downloaded_size == 0 {
+            item_updated.etag = header(ETAG);
+            item_updated.last_modified = header(LAST_MODIFIED);
+         }
+         item_updated
+      };
+      let Some(item_updated) = Download::update_in_progress(app, &item.id, record)? else {
+         return Ok(());
+      };
+      Download::emit(
+         app,
+         DownloadEvent::Progress {
+            download: item_updated.clone(),
+         },
+      );
+
+      // Ensure the output folder exists.
+      if let Some(folder) = Path::new(&temp_path).parent()
+         && !folder.exists()
+      {
+         fs::create_dir(folder)
+            .map_err(|e| Error::File(format!("Failed to create folder: {}", e)))?;
+      }
+
+      // Split the download into segments fetched in parallel, if enabled and supported by the server.
+      if downloaded_size == 0
+         && resumable
+         && let Some(total_size) = total_size
+      {
+         let segments = Download::<R>::plan_segments(&app.download().config, total_size);
+         if segments.len() > 1 {
+            drop(response);
+            return Download::start_segments(app, item_updated, segments).await;
+         }
+      }
+
+      // Open the temp file in append mode, or truncate it if the download is restarting.
+      let mut options = OpenOptions::new();
+      if downloaded_size > 0 {
+         options.append(true);
+      } else {
+         options.write(true).truncate(true);
+      }
+      let mut file = options
+         .create(true)
+         .open(&temp_path)
+         .map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+
+      // Compute the checksum incrementally, starting with the partial download (if any).
+      let mut hasher = if downloaded_size > 0 {
+         Download::<R>::hash_file(&item, &temp_path).await?
+      } else {
+         item
+            .checksum
+            .as_ref()
+            .map(|checksum| ChecksumHasher::new(&checksum.algorithm))
+      };
+
+      // Write the response body to the file in chunks.
+      let rate_limiter = Download::rate_limiter(app, &item.id);
+      let mut downloaded = downloaded_size;
+      let mut stream = response.bytes_stream();
+
+      // Throttle progress updates.
+      let config = &app.download().config;
+      let mut last_emitted_progress = 0.0;
+      let mut speed = SpeedMeter::new(downloaded);
+
+      // Keep the partial download on error so the download can be resumed.
+      while let Some(data) = Download::next_chunk(app, &mut stream).await? {
+         // Wait until the rate limits allow the data to be consumed.
+         app.download().rate_limiter.acquire(data.len() as u64).await;
+         if let Some(rate_limiter) = &rate_limiter {
+            rate_limiter.acquire(data.len() as u64).await;
+         }
+
+         file
+            .write_all(&data)
+            .map_err(|e| Error::File(format!("Failed to write file: {}", e)))?;
+         if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&data);
+         }
+
+         downloaded += data.len() as u64;
+         let progress = total_size
+            .map(|total_size| (downloaded as f64 / total_size as f64) * 100.0)
+            .unwrap_or(0.0);
+         if progress - last_emitted_progress <= config.progress_threshold
+            && speed.elapsed() < Duration::from_millis(config.progress_interval_ms)
+         {
+            // Ignore any progress updates below the threshold.
+            continue;
+         }
+
+         last_emitted_progress = progress;
+         let bytes_per_second = speed.sample(downloaded);
+         let Some(item_progress) = Download::update_in_progress(app, &item.id, |current| {
+            current.with_progress(downloaded, total_size, bytes_per_second)
+         })?
+         else {
+            return Ok(());
+         };
+         Download::emit(
+            app,
+            DownloadEvent::Progress {
+               download: item_progress,
+            },
+         );
+      }
+
+      // Ensure the connection was not closed before the download was completed.
+      if total_size.is_some_and(|total_size| downloaded < total_size) {
+         let error = Error::Network("Connection closed before download completed".to_string());
+         return Err(TransferError::Transient(error, None));
+      }
+
+      Download::complete(app, &item.id, hasher, downloaded)
+   }
+
+   ///
+   /// Completes a download once all bytes have been written to the partial download.
+   /// Verifies the checksum (if any), then moves the partial download to the final path.
+   ///
+   fn complete(
+      app: &AppHandle<R>,
+      id: &str,
+      hasher: Option<ChecksumHasher>,
+      downloaded: u64,
+   ) -> Result<(), TransferError> {
+      // Lock the store until the download is removed, so it cannot be paused or cancelled in the meantime.
+      let lock = store::lock();
+      let Some(item) = Download::current_in_progress(app, id)? else {
+         return Ok(());
+      };
+
+      // Verify the checksum (if any), discarding the partial download on a mismatch as it cannot be resumed.
+      let temp_path = app.download().temp_path(&item.path);
+      if let (Some(checksum), Some(hasher)) = (&item.checksum, hasher)
+         && let Err(e) = checksum::verify(checksum, &hasher.finalize())
+      {
+         fs::remove_file(&temp_path).map_err(Error::from)?;
+         let item_reset = DownloadItem {
+            segments: Vec::new(),
+            ..item.with_progress(0, item.total_bytes, None)
+         };
+         store::update(app, item_reset)?;
+         return Err(e.into());
+      }
+
+      // Rename temp file to final path, remove item from store and emit change event. The item is only removed once
+      // the file has been moved, so the download is marked as failed if it cannot be moved.
+      fs::rename(&temp_path, &item.path).map_err(Error::from)?;
+      store::delete(app, item.id.clone())?;
+      drop(lock);
+      app.download()
+         .sensitive_headers
+         .lock()
+         .unwrap()
+         .remove(&item.id);
+      let item_completed = DownloadItem {
+         segments: Vec::new(),
+         ..item
+            .with_progress(downloaded, Some(downloaded), None)
+            .with_status(DownloadStatus::Completed)
+      };
+      Download::record_history(app, &item_completed, downloaded);
+      Download::emit(
+         app,
+         DownloadEvent::Completed {
+            download: item_completed.clone(),
+            size: downloaded,
+            duration_ms: item
+               .started_at
+               .map(|started_at| now_millis().saturating_sub(started_at)),
+         },
+      );
+      Download::emit(
+         app,
+         DownloadEvent::Removed {
+            download: item_completed,
+         },
+      );
+
+      Ok(())
+   }
+
+   ///
+   /// Sends the request for a download, following redirects as long as each URL is allowed by the URL scopes.
+   /// Credentials are not sent to a different origin, and redirects which change the method to `GET` drop the body.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `item` - The download item.
+   /// - `headers` - The headers for resuming the download.
+   ///
+   /// # Returns
+   /// The response of the final request.
+   async fn send(
+      app: &AppHandle<R>,
+      item: &DownloadItem,
+      headers: HeaderMap,
+   ) -> Result<reqwest::Response, TransferError> {
+      let download = app.download();
+      let mut item = item.clone();
+      let mut url = Url::parse(&item.url).map_err(|_| Error::UrlNotAllowed(item.url.clone()))?;
+      let mut sensitive_headers = Download::sensitive_headers(app, &item.id);
+      let mut redirects = 0;
+
+      loop {
+         download.check_url(&item, &url)?;
+         let response =
+            Download::<R>::request(&download.client, &item, sensitive_headers.as_ref())?
+               .headers(headers.clone())
+               .send()
+               .await
+               .map_err(|e| Download::request_error(app, e, "Failed to send request"))?;
+
+         let status = response.status();
+         let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok());
+         let (true, Some(location)) = (status.is_redirection(), location) else {
+            return Ok(response);
+         };
+
+         redirects += 1;
+         if redirects > download.config.max_redirects {
+            return Err(Error::Http(format!("Too many redirects for {}", item.url)).into());
+         }
+         let next = url
+            .join(location)
+            .map_err(|_| Error::Http(format!("Invalid redirect location: {}", location)))?;
+
+         // Do not send credentials to a different origin.
+         if next.origin() != url.origin() {
+            sensitive_headers = None;
+            if let Some(request) = item.request.as_mut() {
+               request.headers.retain(|name, _| {
+                  ![AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION]
+                     .iter()
+                     .any(|credential| credential.as_str().eq_ignore_ascii_case(name))
+               });
+            }
+         }
+
+         // Follow a `303 See Other`, or a `301`/`302` of a request other than `GET` or `HEAD`, with a `GET`.
+         if let Some(request) = item.request.as_mut() {
+            let method = request.method.as_deref().unwrap_or("GET").to_uppercase();
+            if status == StatusCode::SEE_OTHER
+               || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+                  && method != "GET"
+                  && method != "HEAD")
+            {
+               request.method = None;
+               request.body = None;
+            }
+         }
+
+         // The location is followed as sent, without appending the query of the original request again.
+         if let Some(request) = item.request.as_mut() {
+            request.query.clear();
+         }
+
+         item.url = next.to_string();
+         url = next;
+      }
+   }
+
+   ///
+   /// Builds the request for a download.
+   ///
+   /// # Arguments
+   /// - `client` - The HTTP client.
+   /// - `item` - The download item.
+   /// - `sensitive_headers` - The sensitive header values of the download, if any.
+   ///
+   /// # Returns
+   /// The request, to which the headers for resuming the download are added.
+   fn request(
+      client: &reqwest::Client,
+      item: &DownloadItem,
+      sensitive_headers: Option<&HashMap<String, String>>,
+   ) -> crate::Result<reqwest::RequestBuilder> {
+      let Some(request) = &item.request else {
+         return Ok(client.get(&item.url));
+      };
+
+      let method = match &request.method {
+         Some(method) => reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| Error::InvalidRequest(format!("Invalid method: {}", method)))?,
+         None => reqwest::Method::GET,
+      };
+
+      let mut headers = HeaderMap::new();
+      let sensitive_headers = sensitive_headers.into_iter().flatten();
+      for (name, value, sensitive) in request
+         .headers
+         .iter()
+         .map(|(name, value)| (name, value, false))
+         .chain(sensitive_headers.map(|(name, value)| (name, value, true)))
+      {
+         let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| Error::InvalidRequest(format!("Invalid header name: {}", name)))?;
+         let mut value = HeaderValue::from_str(value)
+            .map_err(|_| Error::InvalidRequest(format!("Invalid value for header: {}", name)))?;
+         value.set_sensitive(sensitive);
+         headers.insert(name, value);
+      }
+
+      let mut builder = client
+         .request(method, &item.url)
+         .query(&request.query)
+         .headers(headers);
+      if let Some(body) = &request.body {
+         builder = builder.body(body.clone());
+      }
+
+      Ok(builder)
+   }
+
+   ///
+   /// Reads the next chunk of a response, failing if no bytes arrive within the stall timeout (if any).
+   ///
+   async fn next_chunk<S, T>(app: &AppHandle<R>, stream: &mut S) -> Result<Option<T>, TransferError>
+   where
+      S: Stream<Item = reqwest::Result<T>> + Unpin,
+   {
+      let config = &app.download().config;
+      let chunk = match config.stall_timeout_ms {
+         Some(timeout) => tokio::time::timeout(Duration::from_millis(timeout), stream.next())
+            .await
+            .map_err(|_| {
+               let error = Error::Network("Transfer stalled".to_string());
+               if config
+                  .retry
+                  .retry_errors
+                  .contains(&TransientErrorKind::Timeout)
+               {
+                  TransferError::Transient(error, None)
+               } else {
+                  TransferError::Permanent(error)
+               }
+            })?,
+         None => stream.next().await,
+      };
+
+      chunk
+         .transpose()
+         .map_err(|e| Download::request_error(app, e, "Failed to download"))
+   }
+
+   ///
+   /// Gets the If-Range header for resuming a download, which ensures the remainder is only sent if the resource has
+   /// not changed since the partial download, otherwise the full resource is sent. Weak ETags cannot be used with
+   /// If-Range.
+   ///
+   fn if_range(item: &DownloadItem) -> Option<HeaderValue> {
+      item
+         .etag
+         .as_ref()
+         .filter(|etag| !etag.starts_with("W/"))
+         .or(item.last_modified.as_ref())
+         .and_then(|validator| validator.parse().ok())
+   }
+
+   ///
+   /// Gets how to resume a download when the server responded to the range request. If the server sent the full
+   /// resource but supports partial downloads, the resource has changed since the partial download so the download is
+   /// restarted. Otherwise the range policy applies.
+   ///
+   /// # Arguments
+   /// - `status` - The status of the response.
+   /// - `resumable` - Whether the server supports partial downloads.
+   /// - `range_policy` - The configured range policy.
+   ///
+   /// # Returns
+   /// The policy to apply, or `None` if the server sent the remainder of the download.
+   fn resume_policy(
+      status: StatusCode,
+      resumable: bool,
+      range_policy: &RangePolicy,
+   ) -> Option<RangePolicy> {
+      if status == StatusCode::PARTIAL_CONTENT {
+         None
+      } else if resumable {
+         Some(RangePolicy::Restart)
+      } else {
+         Some(range_policy.clone())
+      }
+   }
+
+   ///
+   /// Parses the Content-Range header, such as `bytes 100-999/1000` in a partial response or `bytes */1000` in a
+   /// `416 Range Not Satisfiable` response.
+   ///
+   /// # Arguments
+   /// - `headers` - The response headers.
+   ///
+   /// # Returns
+   /// The first byte of the range sent, if any, and the total size of the resource, if known.
+   fn content_range(headers: &HeaderMap) -> Option<(Option<u64>, Option<u64>)> {
+      let (range, total) = headers
+         .get(CONTENT_RANGE)?
+         .to_str()
+         .ok()?
+         .trim()
+         .strip_prefix("bytes ")?
+         .rsplit_once('/')?;
+      let start = range
+         .split_once('-')
+         .and_then(|(start, _)| start.trim().parse::<u64>().ok());
+      Some((start, total.trim().parse::<u64>().ok()))
+   }
+
+   ///
+   /// Gets the total size of the resource from the response headers. A partial response gives the total size in its
+   /// Content-Range header, falling back to the length of the remainder plus the bytes already downloaded.
+   ///
+   /// # Arguments
+   /// - `headers` - The response headers.
+   /// - `downloaded_size` - The number of bytes already downloaded, which are not included in the response.
+   ///
+   /// # Returns
+   /// The total size in bytes, or `None` if unknown.
+   fn total_size(headers: &HeaderMap, downloaded_size: u64) -> Option<u64> {
+      let (_, total_size) = Download::<R>::content_range(headers).unwrap_or_default();
+      total_size.or_else(|| {
+         headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|len| len.parse::<u64>().ok())
+            .map(|len| len + downloaded_size)
+      })
+   }
+
+   ///
+   /// Gets the error for a failed request, which is transient according to the retry policy.
+   ///
+   fn request_error(app: &AppHandle<R>, error: reqwest::Error, message: &str) -> TransferError {
+      if let Some(host) = tls::pin_mismatch(&error) {
+         return TransferError::Permanent(Error::CertificatePin(host));
+      }
+      if let Some(host) = scope::non_public_address(&error) {
+         return TransferError::Permanent(Error::UrlNotAllowed(host));
+      }
+
+      let policy = &app.download().config.retry;
+      let transient = policy.is_transient_error(&error);
+      let error = Error::Network(format!("{}: {}", message, error));
+      if transient {
+         TransferError::Transient(error, None)
+      } else {
+         TransferError::Permanent(error)
+      }
+   }
+
+   ///
+   /// Ensures the server responded successfully. Unsuccessful responses are transient according to the retry policy.
+   ///
+   fn check_status(app: &AppHandle<R>, response: &reqwest::Response) -> Result<(), TransferError> {
+      let policy = &app.download().config.retry;
+      let status = response.status();
+      if status.is_success() {
+         return Ok(());
+      }
+
+      let error = Error::HttpStatus(status.as_u16());
+      if !policy.is_transient_status(status.as_u16()) {
+         return Err(TransferError::Permanent(error));
+      }
+
+      // Honour the delay requested by the server, if any.
+      let retry_after = response
+         .headers()
+         .get(RETRY_AFTER)
+         .and_then(|value| value.to_str().ok())
+         .and_then(|value| value.parse::<u64>().ok())
+         .map(Duration::from_secs);
+      Err(TransferError::Transient(error, retry_after))
+   }
+
+   ///
+   /// Gets the plugin configuration.
+   ///
+   pub(crate) fn config(&self) -> &Config {
+      &self.config
+   }
+
+   ///
+   /// Ensures a download can be written to a path, including its partial download.
+   ///
+   fn check_destination(&self, path: &str) -> crate::Result<()> {
+      self.path_scope.check(path)?;
+      self.path_scope.check(&self.temp_path(path))
+   }
+
+   ///
+   /// Ensures a download can fetch a URL, according to the plugin configuration and the capability scope of the
+   /// window which created or started it.
+   ///
+   fn check_url(&self, item: &DownloadItem, url: &Url) -> crate::Result<()> {
+      self.url_filter.check(url)?;
+      match &item.scope {
+         Some(scope) => scope.check(url.as_str()),
+         None => Ok(()),
+      }
+   }
+
+   ///
+   /// Gets the path of the partial download for a download path.
+   ///
+   fn temp_path(&self, path: &str) -> String {
+      format!("{}{}", path, self.config.temp_suffix)
+   }
+
+   ///
+   /// Gets the rate limiter of a running download.
+   ///
+   fn rate_limiter(app: &AppHandle<R>, id: &str) -> Option<Arc<RateLimiter>> {
+      app.download()
+         .rate_limiters
+         .lock()
+         .unwrap()
+         .get(id)
+         .cloned()
+   }
+
+   ///
+   /// Gets the names of the sensitive headers of a download whose values are not held in memory, such as when the
+   /// application restarted since the download was created.
+   ///
+   fn missing_sensitive_headers(app: &AppHandle<R>, id: &str) -> crate::Result<Vec<String>> {
+      let Some(request) = store::get(app, id.to_string())?.and_then(|item| item.request) else {
+         return Ok(Vec::new());
+      };
+
+      let values = Download::sensitive_headers(app, id).unwrap_or_default();
+      Ok(request
+         .sensitive_headers
+         .into_iter()
+         .filter(|name| !values.keys().any(|value| value.eq_ignore_ascii_case(name)))
+         .collect())
+   }
+
+   ///
+   /// Gets the sensitive header values of a download, if any.
+   ///
+   fn sensitive_headers(app: &AppHandle<R>, id: &str) -> Option<HashMap<String, String>> {
+      app.download()
+         .sensitive_headers
+         .lock()
+         .unwrap()
+         .get(id)
+         .cloned()
+   }
+
+   ///
+   /// Gets the number of bytes downloaded, as last recorded in the store.
+   ///
+   fn downloaded_bytes(app: &AppHandle<R>, id: &str) -> u64 {
+      store::get(app, id.to_string())
+         .ok()
+         .flatten()
+         .map_or(0, |item| item.downloaded_bytes)
+   }
+
+   ///
+   /// Records a finished download in the download history, if enabled. Failing to do so does not fail the download.
+   ///
+   fn record_history(app: &AppHandle<R>, item: &DownloadItem, size: u64) {
+      if let Err(e) = history::record(app, item, size) {
+         eprintln!("[{}] Failed to record download history: {}", item.path, e);
+      }
+   }
+
+   ///
+   /// Computes the checksum of the partial download, if the download has an expected checksum.
+   ///
+   async fn hash_file(
+      item: &DownloadItem,
+      temp_path: &str,
+   ) -> crate::Result<Option<ChecksumHasher>> {
+      match &item.checksum {
+         Some(checksum) => Ok(Some(
+            ChecksumHasher::from_file(&checksum.algorithm, temp_path).await?,
+         )),
+         None => Ok(None),
+      }
+   }
+
+   ///
+   /// Gets a download from the store, if it is still in progress.
+   ///
+   /// # Returns
+   /// The download, or `None` if it was paused, removed or is otherwise no longer in progress, in which case the
+   /// transfer stops.
+   fn current_in_progress(app: &AppHandle<R>, id: &str) -> crate::Result<Option<DownloadItem>> {
+      Ok(store::get(app, id.to_string())?.filter(|item| item.status == DownloadStatus::InProgress))
+   }
+
+   ///
+   /// Changes a download if it is still in progress, such as to record its progress, while the store is locked. A
+   /// download which was paused or removed in the meantime is left unchanged.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID.
+   /// - `change` - Changes the download.
+   ///
+   /// # Returns
+   /// The changed download, or `None` if it is no longer in progress, in which case the transfer stops.
+   fn update_in_progress(
+      app: &AppHandle<R>,
+      id: &str,
+      change: impl FnOnce(DownloadItem) -> DownloadItem,
+   ) -> crate::Result<Option<DownloadItem>> {
+      let _lock = store::lock();
+      let Some(current) = Download::current_in_progress(app, id)? else {
+         return Ok(None);
+      };
+      let item = change(current);
+      store::update(app, item.clone())?;
+
+      Ok(Some(item))
+   }
+
+   ///
+   /// Gets the size of the partial download, if any.
+   ///
+   fn downloaded_size(temp_path: &str) -> u64 {
+      fs::metadata(temp_path)
+         .map(|metadata| metadata.len())
+         .unwrap_or(0)
+   }
+
+   ///
+   /// Emits an event in the lifecycle of a download, as well as the change of the download.
+   ///
+   fn emit(app: &AppHandle<R>, event: DownloadEvent) {
+      // The download was already reported as changed when it was completed or cancelled.
+      if !matches!(event, DownloadEvent::Removed { .. }) {
+         Download::emit_changed(app, event.download().clone());
+      }
+      app.emit(&format!("tauri-plugin-download:{}", event.kind()), &event)
+         .unwrap();
+
+      // Notify the subscribers in Rust. Sending only fails if there are no receivers.
+      let download = app.download();
+      let callbacks: Vec<EventCallback> = download
+         .event_callbacks
+         .lock()
+         .unwrap()
+         .values()
+         .cloned()
+         .collect();
+      for callback in callbacks {
+         callback(&event);
+      }
+      let _ = download.events.send(event);
+   }
+
+   fn emit_changed(app: &AppHandle<R>, item: DownloadItem) {
+      // Send the update to the subscribed channels, dropping any which are closed or no longer needed.
+      let mut channels = app.download().channels.lock().unwrap();
+      if let Some(subscribed) = channels.get_mut(&item.id) {
+         subscribed.retain(|channel| channel.send(item.clone()).is_ok());
+         if subscribed.is_empty()
+            || matches!(
+               item.status,
+               DownloadStatus::Completed | DownloadStatus::Cancelled
+            )
+         {
+            channels.remove(&item.id);
+         }
+      }
+      drop(channels);
+
+      app.emit("tauri-plugin-download:changed", &item).unwrap();
+      println!("[{}] {} - {:.0}%", item.path, item.status, item.progress);
+   }
+}
+
+/// Tracks the smoothed transfer speed of a download.
+struct SpeedMeter {
+   bytes_per_second: Option<f64>,
+   sampled_at: Instant,
+   sampled_bytes: u64,
+}
+
+impl SpeedMeter {
+   fn new(downloaded: u64) -> Self {
+      Self {
+         bytes_per_second: None,
+         sampled_at: Instant::now(),
+         sampled_bytes: downloaded,
+      }
+   }
+
+   ///
+   /// Gets the time elapsed since the last sample.
+   ///
+   fn elapsed(&self) -> Duration {
+      self.sampled_at.elapsed()
+   }
+
+   ///
+   /// Samples the number of bytes downloaded, returning the smoothed transfer speed.
+   ///
+   fn sample(&mut self, downloaded: u64) -> Option<f64> {
+      let elapsed = self.sampled_at.elapsed().as_secs_f64().max(0.001);
+      let sample = downloaded.saturating_sub(self.sampled_bytes) as f64 / elapsed;
+      self.bytes_per_second = Some(match self.bytes_per_second {
+         Some(speed) => SPEED_SMOOTHING * sample + (1.0 - SPEED_SMOOTHING) * speed,
+         None => sample,
+      });
+
+      self.sampled_at = Instant::now();
+      self.sampled_bytes = downloaded;
+      self.bytes_per_second
+   }
+}
+
+///
+/// Creates the HTTP client shared by all downloads, according to the plugin configuration. Redirects are followed by
+/// the download, so each URL can be checked against the URL scopes.
+///
+fn client(config: &Config) -> crate::Result<reqwest::Client> {
+   let mut builder = reqwest::Client::builder().redirect(redirect::Policy::none());
+   if config.url_scope.deny_private_ips {
+      builder = builder.dns_resolver(Arc::new(PublicResolver));
+   }
+   if let Some(timeout) = config.connect_timeout_ms {
+      builder = builder.connect_timeout(Duration::from_millis(timeout));
+   }
+   if let Some(timeout) = config.read_timeout_ms {
+      builder = builder.read_timeout(Duration::from_millis(timeout));
+   }
+   if let Some(user_agent) = &config.user_agent {
+      builder = builder.user_agent(user_agent);
+   }
+   if let Some(tls) = tls::client_config(config)? {
+      builder = builder.use_preconfigured_tls(tls);
+   }
+   if let Some(proxy) = &config.proxy {
+      let proxy = reqwest::Proxy::all(proxy)
+         .map_err(|e| Error::Http(format!("Invalid proxy: {}", e)))?
+         .no_proxy(
+            config
+               .no_proxy
+               .as_deref()
+               .and_then(reqwest::NoProxy::from_string),
+         );
+      builder = builder.proxy(proxy);
+   }
+
+   builder
+      .build()
+      .map_err(|e| Error::Http(format!("Failed to create client: {}", e)))
+}
+
+///
+/// Moves a file, copying it if it cannot be renamed, such as when moving it to another file system.
+///
+fn move_file(from: &str, to: &str) -> std::io::Result<()> {
+   if fs::rename(from, to).is_ok() {
+      return Ok(());
+   }
+
+   fs::copy(from, to)?;
+   fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use tauri::test::MockRuntime;
+
+   fn queued(id: &str, priority: i32, queue_position: Option<i64>) -> DownloadItem {
+      DownloadItem {
+         id: id.to_string(),
+         status: DownloadStatus::Queued,
+         priority,
+         queue_position,
+         ..Default::default()
+      }
+   }
+
+   fn ids(queue: &[DownloadItem]) -> Vec<&str> {
+      queue.iter().map(|item| item.id.as_str()).collect()
+   }
+
+   fn queue() -> Vec<DownloadItem> {
+      vec![
+         queued("a", 0, Some(0)),
+         queued("b", 0, Some(1)),
+         queued("c", 5, Some(2)),
+         queued("d", 0, Some(3)),
+      ]
+   }
+
+   #[test]
+   fn sort_queue_orders_by_priority_position_and_id() {
+      let mut queue = vec![
+         queued("e", 0, Some(1)),
+         queued("d", 0, Some(1)),
+         queued("c", 0, Some(0)),
+         queued("b", -1, Some(0)),
+         queued("a", 1, Some(9)),
+      ];
+      Download::<MockRuntime>::sort_queue(&mut queue);
+      assert_eq!(ids(&queue), vec!["a", "c", "d", "e", "b"]);
+   }
+
+   #[test]
+   fn reorder_moves_before_and_takes_priority() {
+      let mut queue = queue();
+      Download::<MockRuntime>::sort_queue(&mut queue);
+      assert_eq!(ids(&queue), vec!["c", "a", "b", "d"]);
+
+      Download::<MockRuntime>::reorder(&mut queue, "d", "a").unwrap();
+      assert_eq!(ids(&queue), vec!["c", "d", "a", "b"]);
+      assert_eq!(queue[1].priority, 0);
+
+      Download::<MockRuntime>::reorder(&mut queue, "b", "c").unwrap();
+      assert_eq!(ids(&queue), vec!["b", "c", "d", "a"]);
+      assert_eq!(queue[0].priority, 5);
+
+      // Moving a download later in the queue places it before the other download.
+      Download::<MockRuntime>::reorder(&mut queue, "b", "a").unwrap();
+      assert_eq!(ids(&queue), vec!["c", "d", "b", "a"]);
+      assert_eq!(queue[2].priority, 0);
+   }
+
+   #[test]
+   fn reorder_before_itself_keeps_queue() {
+      let mut queue = queue();
+      Download::<MockRuntime>::reorder(&mut queue, "b", "b").unwrap();
+      assert_eq!(ids(&queue), vec!["a", "b", "c", "d"]);
+      assert_eq!(queue[1].priority, 0);
+   }
+
+   #[test]
+   fn reorder_fails_for_downloads_not_queued() {
+      let mut queue = queue();
+      assert_eq!(
+         Download::<MockRuntime>::reorder(&mut queue, "x", "a"),
+         Err("x".to_string())
+      );
+      assert_eq!(
+         Download::<MockRuntime>::reorder(&mut queue, "a", "x"),
+         Err("x".to_string())
+      );
+      assert_eq!(ids(&queue), vec!["a", "b", "c", "d"]);
+   }
+
+   fn total_size(headers: &[(HeaderName, &str)], downloaded_size: u64) -> Option<u64> {
+      let headers = headers
+         .iter()
+         .map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap()))
+         .collect();
+      Download::<MockRuntime>::total_size(&headers, downloaded_size)
+   }
+
+   #[test]
+   fn total_size_from_content_range() {
+      let headers = [
+         (CONTENT_RANGE, "bytes 100-999/1000"),
+         (CONTENT_LENGTH, "900"),
+      ];
+      assert_eq!(total_size(&headers, 100), Some(1000));
+      assert_eq!(
+         total_size(&[(CONTENT_RANGE, "bytes 100-999/1000")], 100),
+         Some(1000)
+      );
+   }
+
+   #[test]
+   fn total_size_from_content_length() {
+      assert_eq!(total_size(&[(CONTENT_LENGTH, "1000")], 0), Some(1000));
+      assert_eq!(total_size(&[(CONTENT_LENGTH, "900")], 100), Some(1000));
+
+      // The total size is unknown in the Content-Range header.
+      let headers = [(CONTENT_RANGE, "bytes 100-999/*"), (CONTENT_LENGTH, "900")];
+      assert_eq!(total_size(&headers, 100), Some(1000));
+   }
+
+   #[test]
+   fn total_size_is_unknown_without_headers() {
+      assert_eq!(total_size(&[], 100), None);
+      assert_eq!(total_size(&[(CONTENT_RANGE, "bytes 100-999/*")], 100), None);
+      assert_eq!(total_size(&[(CONTENT_LENGTH, "unknown")], 0), None);
+   }
+
+   fn content_range(value: &'static str) -> Option<(Option<u64>, Option<u64>)> {
+      let headers = [(CONTENT_RANGE, HeaderValue::from_static(value))]
+         .into_iter()
+         .collect();
+      Download::<MockRuntime>::content_range(&headers)
+   }
+
+   #[test]
+   fn content_range_of_partial_content() {
+      assert_eq!(
+         content_range("bytes 100-999/1000"),
+         Some((Some(100), Some(1000)))
+      );
+      assert_eq!(content_range("bytes 100-999/*"), Some((Some(100), None)));
+   }
+
+   #[test]
+   fn content_range_of_unsatisfiable_range() {
+      assert_eq!(content_range("bytes */1000"), Some((None, Some(1000))));
+   }
+
+   #[test]
+   fn content_range_is_invalid() {
+      assert_eq!(content_range("items 0-9/10"), None);
+      assert_eq!(content_range("bytes 100-999"), None);
+      assert_eq!(
+         Download::<MockRuntime>::content_range(&HeaderMap::new()),
+         None
+      );
+   }
+
+   fn validators(etag: Option<&str>, last_modified: Option<&str>) -> Option<HeaderValue> {
+      let item = DownloadItem {
+         etag: etag.map(str::to_string),
+         last_modified: last_modified.map(str::to_string),
+         ..Default::default()
+      };
+      Download::<MockRuntime>::if_range(&item)
+   }
+
+   #[test]
+   fn if_range_uses_strong_etag() {
+      const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+      assert_eq!(
+         validators(Some("\"abc\""), Some(LAST_MODIFIED)),
+         Some(HeaderValue::from_static("\"abc\""))
+      );
+      assert_eq!(
+         validators(Some("\"abc\""), None),
+         Some(HeaderValue::from_static("\"abc\""))
+      );
+   }
+
+   #[test]
+   fn if_range_falls_back_to_last_modified() {
+      const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+      assert_eq!(
+         validators(Some("W/\"abc\""), Some(LAST_MODIFIED)),
+         Some(HeaderValue::from_static(LAST_MODIFIED))
+      );
+      assert_eq!(
+         validators(None, Some(LAST_MODIFIED)),
+         Some(HeaderValue::from_static(LAST_MODIFIED))
+      );
+   }
+
+   #[test]
+   fn if_range_is_omitted_without_validators() {
+      assert_eq!(validators(None, None), None);
+      assert_eq!(validators(Some("W/\"abc\""), None), None);
+   }
+
+   #[test]
+   fn resume_policy_continues_partial_content() {
+      for range_policy in [RangePolicy::Restart, RangePolicy::Fail, RangePolicy::Pause] {
+         assert_eq!(
+            Download::<MockRuntime>::resume_policy(
+               StatusCode::PARTIAL_CONTENT,
+               true,
+               &range_policy
+            ),
+            None
+         );
+      }
+   }
+
+   #[test]
+   fn resume_policy_restarts_changed_resource() {
+      for range_policy in [RangePolicy::Restart, RangePolicy::Fail, RangePolicy::Pause] {
+         assert_eq!(
+            Download::<MockRuntime>::resume_policy(StatusCode::OK, true, &range_policy),
+            Some(RangePolicy::Restart)
+         );
+      }
+   }
+
+   #[test]
+   fn resume_policy_applies_range_policy_without_range_support() {
+      for range_policy in [RangePolicy::Restart, RangePolicy::Fail, RangePolicy::Pause] {
+         assert_eq!(
+            Download::<MockRuntime>::resume_policy(StatusCode::OK, false, &range_policy),
+            Some(range_policy)
+         );
+      }
    }
 }
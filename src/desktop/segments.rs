@@ -0,0 +1,389 @@
+use futures::future::try_join_all;
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_http::reqwest::header::{HeaderMap, IF_RANGE, RANGE};
+
+use super::{Download, SpeedMeter, TransferError};
+use crate::{Config, DownloadExt, Error, models::*, store};
+
+impl<R: Runtime> Download<R> {
+   ///
+   /// Splits a download into segments, according to the segment configuration.
+   ///
+   /// # Arguments
+   /// - `config` - The plugin configuration.
+   /// - `total_size` - The total size of the download in bytes.
+   ///
+   /// # Returns
+   /// The segments, covering the whole download.
+   pub(super) fn plan_segments(config: &Config, total_size: u64) -> Vec<DownloadSegment> {
+      if total_size == 0 {
+         return Vec::new();
+      }
+
+      let count = (total_size / config.min_segment_size.max(1))
+         .min(config.segments as u64)
+         .max(1);
+      let size = total_size.div_ceil(count);
+      (0..count)
+         .map(|index| index * size)
+         .filter(|start| *start < total_size)
+         .map(|start| DownloadSegment {
+            start,
+            end: (start + size).min(total_size) - 1,
+            downloaded: 0,
+         })
+         .collect()
+   }
+
+   ///
+   /// Starts a segmented download, allocating the partial download and recording the segments.
+   ///
+   pub(super) async fn start_segments(
+      app: &AppHandle<R>,
+      item: DownloadItem,
+      segments: Vec<DownloadSegment>,
+   ) -> Result<(), TransferError> {
+      let temp_path = app.download().temp_path(&item.path);
+      let file = OpenOptions::new()
+         .write(true)
+         .create(true)
+         .truncate(true)
+         .open(&temp_path)
+         .map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+      file
+         .set_len(item.total_bytes.unwrap_or(0))
+         .map_err(|e| Error::File(format!("Failed to allocate file: {}", e)))?;
+
+      let Some(item_segmented) =
+         Download::update_in_progress(app, &item.id, |current| DownloadItem {
+            segments,
+            ..current
+         })?
+      else {
+         return Ok(());
+      };
+      Download::transfer_segments(app, item_segmented).await
+   }
+
+   ///
+   /// Downloads the remaining segments of a segmented download in parallel.
+   ///
+   pub(super) async fn transfer_segments(
+      app: &AppHandle<R>,
+      item: DownloadItem,
+   ) -> Result<(), TransferError> {
+      let total_size = item.total_bytes;
+      let progress: Vec<Arc<AtomicU64>> = item
+         .segments
+         .iter()
+         .map(|segment| Arc::new(AtomicU64::new(segment.downloaded)))
+         .collect();
+
+      let segments = || {
+         item
+            .segments
+            .iter()
+            .zip(&progress)
+            .map(|(segment, downloaded)| DownloadSegment {
+               downloaded: downloaded.load(Ordering::Relaxed),
+               ..segment.clone()
+            })
+            .collect::<Vec<_>>()
+      };
+      let downloaded = || {
+         progress
+            .iter()
+            .map(|downloaded| downloaded.load(Ordering::Relaxed))
+            .sum::<u64>()
+      };
+
+      // Download the incomplete segments. Dropping the transfers cancels any which are still running.
+      let mut transfers = std::pin::pin!(try_join_all(
+         item
+            .segments
+            .iter()
+            .zip(&progress)
+            .filter(|(segment, _)| !segment.is_complete())
+            .map(|(segment, downloaded)| {
+               Download::transfer_segment(app, &item, segment, downloaded.clone())
+            })
+      ));
+
+      // Periodically record the progress of the segments, so the download can be resumed.
+      let interval = Duration::from_millis(app.download().config.progress_interval_ms);
+      let mut ticker = tokio::time::interval(interval);
+      let mut speed = SpeedMeter::new(downloaded());
+      let result = loop {
+         tokio::select! {
+            result = &mut transfers => break result,
+            _ = ticker.tick() => {
+               let bytes_per_second = speed.sample(downloaded());
+               let in_progress = Download::record_segments(
+                  app,
+                  &item.id,
+                  segments(),
+                  downloaded(),
+                  total_size,
+                  bytes_per_second,
+               )?;
+               if !in_progress {
+                  // Download was paused, removed or is otherwise no longer in progress.
+                  return Ok(());
+               }
+            }
+         }
+      };
+
+      // The resource changed since the download started, so restart the download.
+      if let Err(TransferError::Permanent(Error::RangeNotSupported)) = result {
+         return Err(Download::restart_segments(app, &item));
+      }
+
+      if !Download::record_segments(app, &item.id, segments(), downloaded(), total_size, None)? {
+         return Ok(());
+      }
+      result?;
+
+      // Compute the checksum of the whole file, as the segments are downloaded out of order.
+      let temp_path = app.download().temp_path(&item.path);
+      let hasher = Download::<R>::hash_file(&item, &temp_path).await?;
+
+      Download::complete(app, &item.id, hasher, downloaded())
+   }
+
+   ///
+   /// Downloads the remainder of a segment.
+   ///
+   async fn transfer_segment(
+      app: &AppHandle<R>,
+      item: &DownloadItem,
+      segment: &DownloadSegment,
+      downloaded: Arc<AtomicU64>,
+   ) -> Result<(), TransferError> {
+      let mut position = segment.start + downloaded.load(Ordering::Relaxed);
+
+      // Request the remainder of the segment, only if the resource has not changed.
+      let mut headers = HeaderMap::new();
+      headers.insert(
+         RANGE,
+         format!("bytes={}-{}", position, segment.end)
+            .parse()
+            .unwrap(),
+      );
+      if let Some(value) = Download::<R>::if_range(item) {
+         headers.insert(IF_RANGE, value);
+      }
+
+      let response = Download::send(app, item, headers).await?;
+      Download::check_status(app, &response)?;
+
+      // The full resource is sent if the resource has changed.
+      if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+         return Err(Error::RangeNotSupported.into());
+      }
+
+      // Ensure the server sent the requested range, as the data is written from the requested position.
+      let (start, _) = Download::<R>::content_range(response.headers()).unwrap_or_default();
+      if start != Some(position) {
+         return Err(
+            Error::Http(format!(
+               "Expected range starting at byte {}, got {}",
+               position,
+               start.map_or("none".to_string(), |start| start.to_string())
+            ))
+            .into(),
+         );
+      }
+
+      let temp_path = app.download().temp_path(&item.path);
+      let mut file = OpenOptions::new()
+         .write(true)
+         .open(&temp_path)
+         .map_err(|e| Error::File(format!("Failed to open file: {}", e)))?;
+      file
+         .seek(SeekFrom::Start(position))
+         .map_err(|e| Error::File(format!("Failed to seek file: {}", e)))?;
+
+      let rate_limiter = Download::rate_limiter(app, &item.id);
+      let mut stream = response.bytes_stream();
+      while let Some(data) = Download::next_chunk(app, &mut stream).await? {
+         // Wait until the rate limits allow the data to be consumed.
+         app.download().rate_limiter.acquire(data.len() as u64).await;
+         if let Some(rate_limiter) = &rate_limiter {
+            rate_limiter.acquire(data.len() as u64).await;
+         }
+
+         // Ignore any data beyond the end of the segment.
+         let remaining = (segment.end + 1 - position) as usize;
+         let data = &data[..data.len().min(remaining)];
+         file
+            .write_all(data)
+            .map_err(|e| Error::File(format!("Failed to write file: {}", e)))?;
+
+         position += data.len() as u64;
+         downloaded.store(position - segment.start, Ordering::Relaxed);
+         if position > segment.end {
+            return Ok(());
+         }
+      }
+
+      let error = Error::Network("Connection closed before segment completed".to_string());
+      Err(TransferError::Transient(error, None))
+   }
+
+   ///
+   /// Records the progress of the segments of a download.
+   ///
+   /// # Returns
+   /// Whether the download is still in progress.
+   fn record_segments(
+      app: &AppHandle<R>,
+      id: &str,
+      segments: Vec<DownloadSegment>,
+      downloaded: u64,
+      total_size: Option<u64>,
+      bytes_per_second: Option<f64>,
+   ) -> crate::Result<bool> {
+      let lock = store::lock();
+      let Some(current) = store::get(app, id.to_string())? else {
+         return Ok(false);
+      };
+
+      // Keep the progress of a paused download, so it can be resumed.
+      if current.status != DownloadStatus::InProgress {
+         store::update(
+            app,
+            DownloadItem {
+               segments,
+               downloaded_bytes: downloaded,
+               ..current
+            },
+         )?;
+         return Ok(false);
+      }
+
+      let item_progress = DownloadItem {
+         segments,
+         ..current.with_progress(downloaded, total_size, bytes_per_second)
+      };
+      store::update(app, item_progress.clone())?;
+      drop(lock);
+      Download::emit(
+         app,
+         DownloadEvent::Progress {
+            download: item_progress,
+         },
+      );
+      Ok(true)
+   }
+
+   ///
+   /// Discards the partial download of a segmented download so it restarts, returning the error to retry it.
+   ///
+   fn restart_segments(app: &AppHandle<R>, item: &DownloadItem) -> TransferError {
+      let temp_path = app.download().temp_path(&item.path);
+      if let Err(e) = fs::remove_file(&temp_path) {
+         return Error::from(e).into();
+      }
+
+      let reset = |current: &mut DownloadItem| {
+         current.segments.clear();
+         current.downloaded_bytes = 0;
+         current.progress = 0.0;
+         Ok(())
+      };
+      match store::modify(app, item.id.clone(), reset) {
+         Ok(_) | Err(Error::NotFound(_)) => (),
+         Err(e) => return e.into(),
+      }
+
+      let error = Error::Http("Resource changed since the download started".to_string());
+      TransferError::Transient(error, Some(Duration::ZERO))
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use tauri::test::MockRuntime;
+
+   fn plan(segments: usize, min_segment_size: u64, total_size: u64) -> Vec<DownloadSegment> {
+      let config = Config {
+         segments,
+         min_segment_size,
+         ..Config::default()
+      };
+      Download::<MockRuntime>::plan_segments(&config, total_size)
+   }
+
+   fn ranges(segments: &[DownloadSegment]) -> Vec<(u64, u64)> {
+      segments
+         .iter()
+         .map(|segment| (segment.start, segment.end))
+         .collect()
+   }
+
+   #[test]
+   fn plan_segments_splits_evenly() {
+      assert_eq!(
+         ranges(&plan(4, 1, 100)),
+         vec![(0, 24), (25, 49), (50, 74), (75, 99)]
+      );
+   }
+
+   #[test]
+   fn plan_segments_covers_remainders() {
+      assert_eq!(ranges(&plan(3, 1, 10)), vec![(0, 3), (4, 7), (8, 9)]);
+      assert_eq!(ranges(&plan(4, 1, 9)), vec![(0, 2), (3, 5), (6, 8)]);
+
+      for total_size in 1..=64 {
+         for count in 1..=8 {
+            let segments = plan(count, 1, total_size);
+            assert!(!segments.is_empty() && segments.len() <= count);
+            assert_eq!(segments[0].start, 0);
+            assert_eq!(segments[segments.len() - 1].end, total_size - 1);
+            assert!(
+               segments
+                  .windows(2)
+                  .all(|pair| pair[1].start == pair[0].end + 1)
+            );
+            assert!(segments.iter().all(|segment| segment.downloaded == 0));
+         }
+      }
+   }
+
+   #[test]
+   fn plan_segments_limits_count_to_total_size() {
+      assert_eq!(ranges(&plan(8, 1, 3)), vec![(0, 0), (1, 1), (2, 2)]);
+      assert_eq!(ranges(&plan(8, 1, 1)), vec![(0, 0)]);
+      assert!(plan(8, 1, 0).is_empty());
+   }
+
+   #[test]
+   fn plan_segments_respects_min_segment_size() {
+      assert_eq!(ranges(&plan(8, 40, 100)), vec![(0, 49), (50, 99)]);
+      assert_eq!(ranges(&plan(8, 200, 100)), vec![(0, 99)]);
+      assert_eq!(ranges(&plan(8, 0, 4)).len(), 4);
+   }
+
+   #[test]
+   fn resumed_segments_skip_complete_segments() {
+      let mut segments = plan(4, 1, 100);
+      segments[0].downloaded = 25;
+      segments[1].downloaded = 10;
+      segments[3].downloaded = 24;
+
+      let incomplete: Vec<_> = segments
+         .iter()
+         .filter(|segment| !segment.is_complete())
+         .map(|segment| (segment.start + segment.downloaded, segment.end))
+         .collect();
+      assert_eq!(incomplete, vec![(35, 49), (50, 74), (99, 99)]);
+   }
+}
@@ -1,4 +1,6 @@
 use serde::{Serialize, ser::Serializer};
+#[cfg(any(desktop, target_os = "android"))]
+use std::error::Error as StdError;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -19,6 +21,45 @@ pub enum Error {
    #[error("HTTP Error: {0}")]
    Http(String),
 
+   #[error("HTTP Status: {0}")]
+   HttpStatus(u16),
+
+   #[error("Network Error: {0}")]
+   Network(String),
+
+   #[error("Server does not support partial downloads")]
+   RangeNotSupported,
+
+   #[error("Certificate does not match the pinned keys for {0}")]
+   CertificatePin(String),
+
+   #[error("Path not allowed: {0}")]
+   PathNotAllowed(String),
+
+   #[error("URL not allowed: {0}")]
+   UrlNotAllowed(String),
+
+   #[error("Invalid Request: {0}")]
+   InvalidRequest(String),
+
+   #[error("Sensitive headers are not available: {0}")]
+   SensitiveHeadersMissing(String),
+
+   #[error("Download Failed: {}", .0.message)]
+   Failed(crate::DownloadError),
+
+   #[error("Download Cancelled")]
+   Cancelled,
+
+   #[error("Download Paused")]
+   Paused,
+
+   #[error("Checksum Error: {0}")]
+   Checksum(String),
+
+   #[error("Unsupported: {0}")]
+   Unsupported(String),
+
    #[error(transparent)]
    Io(#[from] std::io::Error),
 
@@ -35,3 +76,21 @@ impl Serialize for Error {
       serializer.serialize_str(self.to_string().as_ref())
    }
 }
+
+///
+/// Iterates over an error and its sources. The source of an I/O error is the source of the error it wraps, so the
+/// wrapped error is visited instead.
+///
+#[cfg(any(desktop, target_os = "android"))]
+pub(crate) fn sources<'a>(
+   error: &'a (dyn StdError + 'static),
+) -> impl Iterator<Item = &'a (dyn StdError + 'static)> {
+   std::iter::successors(Some(error), |&error| {
+      match error.downcast_ref::<std::io::Error>() {
+         Some(error) => error
+            .get_ref()
+            .map(|error| error as &(dyn StdError + 'static)),
+         None => error.source(),
+      }
+   })
+}
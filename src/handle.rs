@@ -0,0 +1,175 @@
+use futures::Stream;
+use futures::future::BoxFuture;
+use std::future::IntoFuture;
+use tauri::{AppHandle, Runtime};
+use tokio::sync::mpsc;
+
+use crate::models::*;
+use crate::{DownloadExt, Error};
+
+/// Handle to a started download, which can be awaited for the downloaded file.
+///
+/// The download finishes when it is completed, fails, is cancelled or is paused, including when it is paused because
+/// the server does not support resuming it. The download is cancelled if the handle is dropped before the download
+/// finishes, unless the handle is detached.
+pub struct DownloadHandle<R: Runtime> {
+   app: AppHandle<R>,
+   id: String,
+   /// The ID of the callback which sends the events of the download to the handle.
+   callback_id: u64,
+   events: mpsc::UnboundedReceiver<DownloadEvent>,
+   /// The result of the download, once it has finished.
+   outcome: Option<crate::Result<DownloadItem>>,
+   /// Whether the download is left running when the handle is dropped.
+   detached: bool,
+}
+
+impl<R: Runtime> DownloadHandle<R> {
+   ///
+   /// Starts a download, or resumes it if it is paused or failed.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID or path.
+   ///
+   /// # Returns
+   /// The handle to the download.
+   pub(crate) fn start(app: AppHandle<R>, id: String) -> crate::Result<Self> {
+      let item = app.download().get(app.clone(), id.clone())?;
+      if item.status == DownloadStatus::Pending {
+         return Err(Error::NotFound(id));
+      }
+      let id = item.id.clone();
+
+      // Receive the events of the download before it is started, so none are missed.
+      let (sender, events) = mpsc::unbounded_channel();
+      let watched_id = id.clone();
+      let callback_id = app.download().on_event(move |event| {
+         if event.download().id == watched_id {
+            let _ = sender.send(event.clone());
+         }
+      })?;
+
+      let response = match item.status {
+         DownloadStatus::Paused | DownloadStatus::Failed => {
+            app.download().resume(app.clone(), id.clone())
+         }
+         _ => app.download().start(app.clone(), id.clone()),
+      };
+      if let Err(e) = response {
+         let _ = app.download().remove_event_callback(callback_id);
+         return Err(e);
+      }
+
+      Ok(Self {
+         app,
+         id,
+         callback_id,
+         events,
+         outcome: None,
+         detached: false,
+      })
+   }
+
+   ///
+   /// Gets the download ID.
+   ///
+   pub fn id(&self) -> &str {
+      &self.id
+   }
+
+   ///
+   /// Gets a stream of the progress of the download, which ends once the download has finished. The result of the
+   /// download is kept, so the handle can then be awaited.
+   ///
+   pub fn progress(&mut self) -> impl Stream<Item = DownloadItem> + '_ {
+      futures::stream::unfold(self, |handle| async move {
+         while handle.outcome.is_none() {
+            let event = handle.events.recv().await?;
+            if let Some(item) = handle.record(event) {
+               return Some((item, handle));
+            }
+         }
+         None
+      })
+   }
+
+   ///
+   /// Cancels the download.
+   ///
+   pub fn cancel(mut self) -> crate::Result<()> {
+      self.detached = true;
+      self
+         .app
+         .download()
+         .cancel(self.app.clone(), self.id.clone())
+         .map(|_| ())
+   }
+
+   ///
+   /// Leaves the download running without the handle, so it is not cancelled when the handle is dropped.
+   ///
+   pub fn detach(mut self) {
+      self.detached = true;
+   }
+
+   ///
+   /// Waits until the download has finished.
+   ///
+   /// # Returns
+   /// The completed download, or an error if the download failed, was cancelled or was paused. A paused download is
+   /// left paused, so it can be resumed with a new handle.
+   async fn wait(mut self) -> crate::Result<DownloadItem> {
+      while self.outcome.is_none() {
+         match self.events.recv().await {
+            Some(event) => {
+               self.record(event);
+            }
+            None => break,
+         }
+      }
+
+      self.detached = true;
+      self.outcome.take().unwrap_or(Err(Error::InvalidState))
+   }
+
+   ///
+   /// Records the result of the download if the event finished it.
+   ///
+   /// # Returns
+   /// The download, if the event reports its progress.
+   fn record(&mut self, event: DownloadEvent) -> Option<DownloadItem> {
+      match event {
+         DownloadEvent::Started { download }
+         | DownloadEvent::Resumed { download }
+         | DownloadEvent::Progress { download } => return Some(download),
+         DownloadEvent::Completed { download, .. } => self.outcome = Some(Ok(download)),
+         DownloadEvent::Failed { error, .. } => self.outcome = Some(Err(Error::Failed(error))),
+         DownloadEvent::Cancelled { .. } => self.outcome = Some(Err(Error::Cancelled)),
+         DownloadEvent::Paused { .. } => self.outcome = Some(Err(Error::Paused)),
+         _ => (),
+      }
+      None
+   }
+}
+
+impl<R: Runtime> IntoFuture for DownloadHandle<R> {
+   type Output = crate::Result<DownloadItem>;
+   type IntoFuture = BoxFuture<'static, Self::Output>;
+
+   fn into_future(self) -> Self::IntoFuture {
+      Box::pin(self.wait())
+   }
+}
+
+impl<R: Runtime> Drop for DownloadHandle<R> {
+   fn drop(&mut self) {
+      let _ = self.app.download().remove_event_callback(self.callback_id);
+      if !self.detached && self.outcome.is_none() {
+         let _ = self
+            .app
+            .download()
+            .cancel(self.app.clone(), self.id.clone());
+      }
+   }
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::{Store, StoreExt};
+
+use crate::models::*;
+use crate::{DownloadExt, Error};
+
+///
+/// Records a finished download in the history, if the history is enabled.
+///
+/// # Arguments
+/// - `app` - The application handle.
+/// - `item` - The finished download item, whose status is the outcome of the download.
+/// - `size` - The number of bytes downloaded.
+pub fn record<R: Runtime>(app: &AppHandle<R>, item: &DownloadItem, size: u64) -> crate::Result<()> {
+   if app.download().config().history_path.is_none() {
+      return Ok(());
+   }
+
+   let finished_at = now_millis();
+   let duration_ms = item
+      .started_at
+      .map(|started_at| finished_at.saturating_sub(started_at));
+   let record = DownloadRecord {
+      id: item.id.clone(),
+      url: item.url.clone(),
+      path: item.path.clone(),
+      status: item.status.clone(),
+      started_at: item.started_at,
+      finished_at,
+      size,
+      duration_ms,
+      average_bytes_per_second: duration_ms
+         .filter(|duration_ms| *duration_ms > 0)
+         .map(|duration_ms| size as f64 * 1000.0 / duration_ms as f64),
+   };
+
+   let store = store(app)?;
+   store.set(&record.id, serde_json::to_value(&record).unwrap());
+   store
+      .save()
+      .map_err(|e| Error::Store(format!("Failed to save history: {}", e)))
+}
+
+pub fn list<R: Runtime>(app: &AppHandle<R>, query: HistoryQuery) -> crate::Result<HistoryPage> {
+   let store = store(app)?;
+
+   let records = store
+      .entries()
+      .into_iter()
+      .map(|(_, value)| {
+         serde_json::from_value(value)
+            .map_err(|e| Error::Store(format!("Failed to parse record: {}", e)))
+      })
+      .collect::<crate::Result<Vec<DownloadRecord>>>()?;
+
+   Ok(page(records, &query))
+}
+
+///
+/// Filters and paginates the recorded downloads, most recently finished first.
+///
+/// # Arguments
+/// - `records` - The recorded downloads.
+/// - `query` - The filters and pagination.
+fn page(records: Vec<DownloadRecord>, query: &HistoryQuery) -> HistoryPage {
+   let mut records: Vec<_> = records
+      .into_iter()
+      .filter(|record| {
+         query
+            .status
+            .as_ref()
+            .is_none_or(|status| *status == record.status)
+            && query.from.is_none_or(|from| record.finished_at >= from)
+            && query.to.is_none_or(|to| record.finished_at < to)
+      })
+      .collect();
+
+   records.sort_by(|a, b| (b.finished_at, &b.id).cmp(&(a.finished_at, &a.id)));
+   let total = records.len();
+   let records = records
+      .into_iter()
+      .skip(query.offset)
+      .take(query.limit.unwrap_or(usize::MAX))
+      .collect();
+
+   HistoryPage { records, total }
+}
+
+pub fn remove<R: Runtime>(app: &AppHandle<R>, id: String) -> crate::Result<()> {
+   let store = store(app)?;
+
+   if store.has(&id) {
+      store.delete(&id);
+   }
+
+   store
+      .save()
+      .map_err(|e| Error::Store(format!("Failed to save history: {}", e)))
+}
+
+pub fn clear<R: Runtime>(app: &AppHandle<R>) -> crate::Result<()> {
+   let store = store(app)?;
+
+   store.clear();
+   store
+      .save()
+      .map_err(|e| Error::Store(format!("Failed to save history: {}", e)))
+}
+
+///
+/// Gets the store which records finished downloads, failing if the history is disabled.
+///
+fn store<R: Runtime>(app: &AppHandle<R>) -> crate::Result<Arc<Store<R>>> {
+   let history_path = app
+      .download()
+      .config()
+      .history_path
+      .as_ref()
+      .ok_or_else(|| Error::Store("Download history is disabled".to_string()))?;
+
+   app.store(history_path)
+      .map_err(|e| Error::Store(format!("Failed to load history: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn record(id: &str, status: DownloadStatus, finished_at: u64) -> DownloadRecord {
+      DownloadRecord {
+         id: id.to_string(),
+         url: format!("https://example.com/{}", id),
+         path: format!("/downloads/{}", id),
+         status,
+         started_at: None,
+         finished_at,
+         size: 0,
+         duration_ms: None,
+         average_bytes_per_second: None,
+      }
+   }
+
+   fn records() -> Vec<DownloadRecord> {
+      vec![
+         record("a", DownloadStatus::Completed, 100),
+         record("b", DownloadStatus::Cancelled, 200),
+         record("c", DownloadStatus::Completed, 300),
+         record("d", DownloadStatus::Completed, 300),
+         record("e", DownloadStatus::Cancelled, 400),
+      ]
+   }
+
+   fn ids(page: &HistoryPage) -> Vec<&str> {
+      page
+         .records
+         .iter()
+         .map(|record| record.id.as_str())
+         .collect()
+   }
+
+   #[test]
+   fn page_sorts_most_recent_first() {
+      let history = page(records(), &HistoryQuery::default());
+      assert_eq!(ids(&history), vec!["e", "d", "c", "b", "a"]);
+      assert_eq!(history.total, 5);
+   }
+
+   #[test]
+   fn page_filters_by_status() {
+      let query = HistoryQuery {
+         status: Some(DownloadStatus::Completed),
+         ..HistoryQuery::default()
+      };
+      let history = page(records(), &query);
+      assert_eq!(ids(&history), vec!["d", "c", "a"]);
+      assert_eq!(history.total, 3);
+   }
+
+   #[test]
+   fn page_filters_by_date() {
+      let query = HistoryQuery {
+         from: Some(200),
+         to: Some(400),
+         ..HistoryQuery::default()
+      };
+      let history = page(records(), &query);
+      assert_eq!(ids(&history), vec!["d", "c", "b"]);
+      assert_eq!(history.total, 3);
+
+      let query = HistoryQuery {
+         status: Some(DownloadStatus::Cancelled),
+         from: Some(201),
+         ..HistoryQuery::default()
+      };
+      assert_eq!(ids(&page(records(), &query)), vec!["e"]);
+   }
+
+   #[test]
+   fn page_applies_offset_and_limit() {
+      let query = HistoryQuery {
+         offset: 1,
+         limit: Some(2),
+         ..HistoryQuery::default()
+      };
+      let history = page(records(), &query);
+      assert_eq!(ids(&history), vec!["d", "c"]);
+      assert_eq!(history.total, 5);
+
+      let query = HistoryQuery {
+         offset: 10,
+         ..HistoryQuery::default()
+      };
+      let history = page(records(), &query);
+      assert!(history.records.is_empty());
+      assert_eq!(history.total, 5);
+   }
+
+   #[test]
+   fn records_with_previous_speed_field_are_read() {
+      let record: DownloadRecord = serde_json::from_value(serde_json::json!({
+         "id": "a",
+         "url": "https://example.com/a",
+         "path": "/downloads/a",
+         "status": "completed",
+         "finishedAt": 2000,
+         "size": 1000,
+         "durationMs": 1000,
+         "bytesPerSecond": 1000.0,
+      }))
+      .unwrap();
+      assert_eq!(record.average_bytes_per_second, Some(1000.0));
+   }
+}
@@ -1,22 +1,49 @@
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::utils::config::FsScope;
+use tauri::webview::PageLoadEvent;
 use tauri::{
-   Manager, RunEvent, Runtime,
-   plugin::{Builder, TauriPlugin},
+   AppHandle, Manager, RunEvent, Runtime, WindowEvent,
+   plugin::{Builder as PluginBuilder, TauriPlugin},
 };
 
-use error::{Error, Result};
-use models::*;
+pub use config::*;
+pub use error::{Error, Result};
+pub use handle::DownloadHandle;
+pub use models::{
+   CapabilityScope, Checksum, ChecksumAlgorithm, DownloadError, DownloadErrorKind, DownloadEvent,
+   DownloadItem, DownloadRecord, DownloadRefresh, DownloadRequest, DownloadRetry, DownloadSegment,
+   DownloadStatus, HistoryPage, HistoryQuery, RefreshReason, RefreshRequest, UrlScopeEntry,
+};
+#[cfg(any(desktop, target_os = "android"))]
 use tauri_plugin_store::StoreExt;
 
 mod commands;
+mod config;
 mod error;
+mod handle;
 mod models;
 
+#[cfg(any(desktop, target_os = "android"))]
+mod checksum;
 #[cfg(any(desktop, target_os = "android"))]
 mod desktop;
 #[cfg(any(desktop, target_os = "android"))]
 use desktop::Download;
 #[cfg(any(desktop, target_os = "android"))]
+mod history;
+#[cfg(any(desktop, target_os = "android"))]
+mod scheduler;
+#[cfg(any(desktop, target_os = "android"))]
+mod scope;
+#[cfg(any(desktop, target_os = "android"))]
 mod store;
+#[cfg(any(desktop, target_os = "android"))]
+mod throttle;
+#[cfg(any(desktop, target_os = "android"))]
+mod tls;
 
 #[cfg(target_os = "ios")]
 mod mobile;
@@ -34,46 +61,335 @@ impl<R: Runtime, T: Manager<R>> crate::DownloadExt<R> for T {
    }
 }
 
-/// Initializes the plugin.
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-   Builder::new("download")
-      .invoke_handler(tauri::generate_handler![
-         commands::create,
-         commands::list,
-         commands::get,
-         commands::start,
-         commands::cancel,
-         commands::pause,
-         commands::resume,
-         commands::is_native,
-      ])
-      .setup(|app, api| {
-         #[cfg(any(desktop, target_os = "android"))]
-         let download = desktop::init(app, api)?;
-
-         #[cfg(target_os = "ios")]
-         let download = mobile::init(app, api)?;
-
-         app.manage(download);
-         if cfg!(any(desktop, target_os = "android")) {
-            // Initialize the store plugin.
-            // https://docs.rs/tauri/latest/tauri/struct.AppHandle.html#method.plugin
-            let handle = app.app_handle().clone();
-            std::thread::spawn(move || {
-               handle
-                  .plugin(tauri_plugin_store::Builder::new().build())
-                  .unwrap();
-               handle.store("downloads.json").unwrap();
-            });
-         }
-
-         Ok(())
+/// Hook which refreshes the request of a download, such as an expired signed URL or bearer token.
+pub type RefreshHook<R> = Arc<
+   dyn Fn(AppHandle<R>, RefreshRequest) -> BoxFuture<'static, Result<Option<DownloadRefresh>>>
+      + Send
+      + Sync,
+>;
+
+/// Setting applied to the plugin configuration by the builder.
+type Configure = Box<dyn Fn(&mut Config) + Send + Sync>;
+
+/// Builder for the download plugin.
+///
+/// Settings set on the builder take precedence over the plugin configuration in `tauri.conf.json`. Settings are not
+/// supported on iOS.
+#[cfg_attr(target_os = "ios", allow(dead_code))]
+pub struct Builder<R: Runtime> {
+   config: Vec<Configure>,
+   refresh_hook: Option<RefreshHook<R>>,
+}
+
+impl<R: Runtime> Default for Builder<R> {
+   fn default() -> Self {
+      Self {
+         config: Vec::new(),
+         refresh_hook: None,
+      }
+   }
+}
+
+impl<R: Runtime> Builder<R> {
+   pub fn new() -> Self {
+      Self::default()
+   }
+
+   ///
+   /// Sets the policy for retrying downloads after a transient error.
+   ///
+   pub fn retry(self, retry: RetryPolicy) -> Self {
+      self.configure(move |config| config.retry = retry.clone())
+   }
+
+   ///
+   /// Sets the maximum number of downloads which can run at once. Further downloads are queued.
+   ///
+   pub fn max_concurrent(self, max_concurrent: usize) -> Self {
+      self.configure(move |config| config.max_concurrent = max_concurrent)
+   }
+
+   ///
+   /// Sets the maximum number of downloads which can run at once for a host.
+   ///
+   /// # Arguments
+   /// - `host` - The host name.
+   /// - `max_concurrent` - The maximum number of downloads.
+   pub fn max_concurrent_per_host(self, host: impl Into<String>, max_concurrent: usize) -> Self {
+      let host = host.into();
+      self.configure(move |config| {
+         config
+            .max_concurrent_per_host
+            .insert(host.clone(), max_concurrent);
       })
-      .on_event(|app_handle, event| {
-         if let RunEvent::Ready = event {
-            // Initialize the download plugin.
-            app_handle.state::<Download<R>>().init();
-         }
+   }
+
+   ///
+   /// Sets how to resume a download when the server does not support partial downloads.
+   ///
+   pub fn range_policy(self, range_policy: RangePolicy) -> Self {
+      self.configure(move |config| config.range_policy = range_policy.clone())
+   }
+
+   ///
+   /// Sets the total rate limit of all downloads in bytes per second.
+   ///
+   pub fn rate_limit(self, rate_limit: u64) -> Self {
+      self.configure(move |config| config.rate_limit = Some(rate_limit))
+   }
+
+   ///
+   /// Sets the maximum number of segments a download is split into and downloaded in parallel.
+   ///
+   pub fn segments(self, segments: usize) -> Self {
+      self.configure(move |config| config.segments = segments)
+   }
+
+   ///
+   /// Sets the minimum size of each segment in bytes.
+   ///
+   pub fn min_segment_size(self, min_segment_size: u64) -> Self {
+      self.configure(move |config| config.min_segment_size = min_segment_size)
+   }
+
+   ///
+   /// Sets the file name of the store which persists downloads.
+   ///
+   pub fn store_path(self, store_path: impl Into<String>) -> Self {
+      let store_path = store_path.into();
+      self.configure(move |config| config.store_path = store_path.clone())
+   }
+
+   ///
+   /// Sets the file name of the store which records finished downloads, enabling the download history.
+   ///
+   pub fn history_path(self, history_path: impl Into<String>) -> Self {
+      let history_path = history_path.into();
+      self.configure(move |config| config.history_path = Some(history_path.clone()))
+   }
+
+   ///
+   /// Sets the suffix appended to the path of a partial download.
+   ///
+   pub fn temp_suffix(self, temp_suffix: impl Into<String>) -> Self {
+      let temp_suffix = temp_suffix.into();
+      self.configure(move |config| config.temp_suffix = temp_suffix.clone())
+   }
+
+   ///
+   /// Sets the minimum increase in progress, in percent, before a progress update is emitted.
+   ///
+   pub fn progress_threshold(self, progress_threshold: f64) -> Self {
+      self.configure(move |config| config.progress_threshold = progress_threshold)
+   }
+
+   ///
+   /// Sets the maximum time between progress updates.
+   ///
+   pub fn progress_interval(self, progress_interval: Duration) -> Self {
+      let progress_interval_ms = progress_interval.as_millis() as u64;
+      self.configure(move |config| config.progress_interval_ms = progress_interval_ms)
+   }
+
+   ///
+   /// Sets the timeout for establishing a connection.
+   ///
+   pub fn connect_timeout(self, timeout: Duration) -> Self {
+      let timeout_ms = timeout.as_millis() as u64;
+      self.configure(move |config| config.connect_timeout_ms = Some(timeout_ms))
+   }
+
+   ///
+   /// Sets the timeout for each read of a response.
+   ///
+   pub fn read_timeout(self, timeout: Duration) -> Self {
+      let timeout_ms = timeout.as_millis() as u64;
+      self.configure(move |config| config.read_timeout_ms = Some(timeout_ms))
+   }
+
+   ///
+   /// Sets the `User-Agent` header sent with each request.
+   ///
+   pub fn user_agent(self, user_agent: impl Into<String>) -> Self {
+      let user_agent = user_agent.into();
+      self.configure(move |config| config.user_agent = Some(user_agent.clone()))
+   }
+
+   ///
+   /// Sets the proxy for all requests, such as `http://proxy:8080` or `socks5://proxy:1080`.
+   ///
+   pub fn proxy(self, proxy: impl Into<String>) -> Self {
+      let proxy = proxy.into();
+      self.configure(move |config| config.proxy = Some(proxy.clone()))
+   }
+
+   ///
+   /// Sets the hosts which bypass the proxy, separated by commas.
+   ///
+   pub fn no_proxy(self, no_proxy: impl Into<String>) -> Self {
+      let no_proxy = no_proxy.into();
+      self.configure(move |config| config.no_proxy = Some(no_proxy.clone()))
+   }
+
+   ///
+   /// Sets the maximum number of redirects to follow. Set to 0 to disable redirects.
+   ///
+   pub fn max_redirects(self, max_redirects: usize) -> Self {
+      self.configure(move |config| config.max_redirects = max_redirects)
+   }
+
+   ///
+   /// Adds a trusted root certificate.
+   ///
+   /// # Arguments
+   /// - `certificate` - The PEM encoded certificate, or the path to a PEM file.
+   pub fn root_certificate(self, certificate: impl Into<String>) -> Self {
+      let certificate = certificate.into();
+      self.configure(move |config| config.root_certificates.push(certificate.clone()))
+   }
+
+   ///
+   /// Pins the public keys of a host. Connections to the host fail unless its certificate matches one of the pins.
+   ///
+   /// # Arguments
+   /// - `host` - The host name.
+   /// - `pins` - The base64 encoded SHA-256 hashes of the `SubjectPublicKeyInfo` of the allowed certificates.
+   pub fn certificate_pins(self, host: impl Into<String>, pins: Vec<String>) -> Self {
+      let host = host.into();
+      self.configure(move |config| {
+         config.certificate_pins.insert(host.clone(), pins.clone());
       })
-      .build()
+   }
+
+   ///
+   /// Sets the paths downloads can be written to, which may use path variables such as `$DOWNLOAD`.
+   ///
+   pub fn destination_scope(self, scope: FsScope) -> Self {
+      self.configure(move |config| config.destination_scope = Some(scope.clone()))
+   }
+
+   ///
+   /// Sets the URLs which can be downloaded, checked when a download is created and on every redirect.
+   ///
+   pub fn url_scope(self, scope: UrlScope) -> Self {
+      self.configure(move |config| config.url_scope = scope.clone())
+   }
+
+   ///
+   /// Sets the time without receiving any bytes after which a transfer fails.
+   ///
+   pub fn stall_timeout(self, timeout: Duration) -> Self {
+      let timeout_ms = timeout.as_millis() as u64;
+      self.configure(move |config| config.stall_timeout_ms = Some(timeout_ms))
+   }
+
+   ///
+   /// Sets the hook which refreshes the request of a download, such as an expired signed URL or bearer token.
+   /// The hook is called when the server responds with `401 Unauthorized` or `403 Forbidden`, before a partial
+   /// download is resumed, and when the values of sensitive headers were lost because the application restarted. It
+   /// returns a new URL and/or headers, or `None` to leave the request unchanged. The download then continues from
+   /// its current offset. Takes precedence over a hook registered from JavaScript with `onRefresh`. Not supported on
+   /// iOS.
+   ///
+   /// # Arguments
+   /// - `hook` - The hook.
+   pub fn on_refresh<F, Fut>(mut self, hook: F) -> Self
+   where
+      F: Fn(AppHandle<R>, RefreshRequest) -> Fut + Send + Sync + 'static,
+      Fut: Future<Output = Result<Option<DownloadRefresh>>> + Send + 'static,
+   {
+      self.refresh_hook = Some(Arc::new(move |app, request| Box::pin(hook(app, request))));
+      self
+   }
+
+   fn configure(mut self, configure: impl Fn(&mut Config) + Send + Sync + 'static) -> Self {
+      self.config.push(Box::new(configure));
+      self
+   }
+
+   ///
+   /// Builds the plugin.
+   ///
+   pub fn build(self) -> TauriPlugin<R, Option<Config>> {
+      PluginBuilder::<R, Option<Config>>::new("download")
+         .invoke_handler(tauri::generate_handler![
+            commands::create,
+            commands::list,
+            commands::get,
+            commands::start,
+            commands::cancel,
+            commands::pause,
+            commands::resume,
+            commands::set_priority,
+            commands::move_to_front,
+            commands::move_before,
+            commands::set_rate_limit,
+            commands::set_refresh_listener,
+            commands::refresh,
+            commands::subscribe,
+            commands::unsubscribe,
+            commands::set_destination,
+            commands::set_url,
+            commands::history,
+            commands::clear_history,
+            commands::remove_from_history,
+            commands::is_native,
+         ])
+         .setup(move |app, api| {
+            #[cfg(any(desktop, target_os = "android"))]
+            let download = desktop::init(
+               app,
+               api,
+               |config| self.config.iter().for_each(|configure| configure(config)),
+               self.refresh_hook,
+            )?;
+
+            #[cfg(target_os = "ios")]
+            let download = mobile::init(app, api)?;
+
+            app.manage(download);
+            #[cfg(any(desktop, target_os = "android"))]
+            {
+               // Initialize the store plugin.
+               // https://docs.rs/tauri/latest/tauri/struct.AppHandle.html#method.plugin
+               let handle = app.app_handle().clone();
+               let store_path = app.download().config().store_path.clone();
+               std::thread::spawn(move || {
+                  handle
+                     .plugin(tauri_plugin_store::Builder::new().build())
+                     .unwrap();
+                  handle.store(store_path).unwrap();
+               });
+            }
+
+            Ok(())
+         })
+         .on_page_load(|webview, payload| {
+            // The hooks registered by the previous page can no longer answer refresh requests.
+            if payload.event() == PageLoadEvent::Started {
+               webview.download().remove_refresh_listeners(webview.label());
+            }
+         })
+         .on_event(|app_handle, event| match event {
+            RunEvent::Ready => {
+               // Initialize the download plugin.
+               app_handle.state::<Download<R>>().init();
+            }
+            RunEvent::WindowEvent {
+               label,
+               event: WindowEvent::Destroyed,
+               ..
+            } => {
+               // The hooks registered by the window can no longer answer refresh requests.
+               app_handle.download().remove_refresh_listeners(label);
+            }
+            _ => (),
+         })
+         .build()
+   }
+}
+
+/// Initializes the plugin.
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<Config>> {
+   Builder::new().build()
 }
@@ -1,8 +1,11 @@
 use serde::de::DeserializeOwned;
+use tauri::ipc::Channel;
 use tauri::plugin::{PluginApi, PluginHandle};
 use tauri::{AppHandle, Runtime};
+use tokio::sync::broadcast;
 
 use crate::models::*;
+use crate::{DownloadHandle, Error};
 
 #[cfg(target_os = "ios")]
 tauri::ios_plugin_binding!(init_plugin_download);
@@ -49,14 +52,14 @@ impl<R: Runtime> Download<R> {
    /// persist it and transition to `Idle` state.
    ///
    /// # Arguments
-   /// - `path` - The download path.
+   /// - `id` - The download ID, which is the download path on iOS.
    ///
    /// # Returns
    /// The download operation.
-   pub fn get(&self, _app: AppHandle<R>, path: String) -> crate::Result<DownloadItem> {
+   pub fn get(&self, _app: AppHandle<R>, id: String) -> crate::Result<DownloadItem> {
       self
          .0
-         .run_mobile_plugin("get", PathArgs { path })
+         .run_mobile_plugin("get", PathArgs { path: id })
          .map_err(Into::into)
    }
 
@@ -67,6 +70,10 @@ impl<R: Runtime> Download<R> {
    /// - `app` - The application handle.
    /// - `path` - The download path.
    /// - `url` - The download URL for the resource.
+   /// - `checksum` - The expected checksum. Not supported on iOS.
+   /// - `request` - The request sent to download the resource. Not supported on iOS.
+   /// - `scope` - The URLs granted to the calling window through the capability system, if called from a window. Only
+   ///   the download URL is checked on iOS, as redirects are followed by the system.
    ///
    /// # Returns
    /// The download operation.
@@ -75,7 +82,20 @@ impl<R: Runtime> Download<R> {
       _app: AppHandle<R>,
       path: String,
       url: String,
+      checksum: Option<Checksum>,
+      request: Option<DownloadRequest>,
+      scope: Option<CapabilityScope>,
    ) -> crate::Result<DownloadActionResponse> {
+      if let Some(scope) = &scope {
+         scope.check(&url)?;
+      }
+      if checksum.is_some() {
+         return Err(Error::Unsupported("checksum".to_string()));
+      }
+      if request.is_some() {
+         return Err(Error::Unsupported("request".to_string()));
+      }
+
       self
          .0
          .run_mobile_plugin("create", CreateArgs { path, url })
@@ -87,46 +107,86 @@ impl<R: Runtime> Download<R> {
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID, which is the download path on iOS.
    ///
    /// # Returns
    /// The download operation.
-   pub fn start(&self, _app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
+   pub fn start(&self, _app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
       self
          .0
-         .run_mobile_plugin("start", PathArgs { path })
+         .run_mobile_plugin("start", PathArgs { path: id })
          .map_err(Into::into)
    }
 
+   ///
+   /// Starts a download operation, or resumes it if it is paused or failed, returning a handle which can be awaited
+   /// until the download has finished. The download is cancelled if the handle is dropped before then, unless the
+   /// handle is detached. Not supported on iOS, where events are only sent to JavaScript.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID, which is the download path on iOS.
+   ///
+   /// # Returns
+   /// The handle to the download.
+   pub fn start_with_handle(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+   ) -> crate::Result<DownloadHandle<R>> {
+      DownloadHandle::start(app, id)
+   }
+
    ///
    /// Resumes a download operation.
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID, which is the download path on iOS.
    ///
    /// # Returns
    /// The download operation.
-   pub fn resume(&self, _app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
+   pub fn resume(&self, _app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
       self
          .0
-         .run_mobile_plugin("resume", PathArgs { path })
+         .run_mobile_plugin("resume", PathArgs { path: id })
          .map_err(Into::into)
    }
 
+   ///
+   /// Ensures the URL of a download is allowed by the capability scope of the window which starts or resumes it.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID, which is the download path on iOS.
+   /// - `scope` - The URLs granted to the calling window through the capability system.
+   pub(crate) fn check_scope(
+      &self,
+      app: AppHandle<R>,
+      id: String,
+      scope: CapabilityScope,
+   ) -> crate::Result<()> {
+      let item = self.get(app, id.clone())?;
+      if item.status == DownloadStatus::Pending {
+         return Err(Error::NotFound(id));
+      }
+
+      scope.check(&item.url)
+   }
+
    ///
    /// Pauses a download operation.
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID, which is the download path on iOS.
    ///
    /// # Returns
    /// The download operation.
-   pub fn pause(&self, _app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
+   pub fn pause(&self, _app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
       self
          .0
-         .run_mobile_plugin("pause", PathArgs { path })
+         .run_mobile_plugin("pause", PathArgs { path: id })
          .map_err(Into::into)
    }
 
@@ -135,14 +195,203 @@ impl<R: Runtime> Download<R> {
    ///
    /// # Arguments
    /// - `app` - The application handle.
-   /// - `path` - The download path.
+   /// - `id` - The download ID, which is the download path on iOS.
+   ///
+   /// # Returns
+   /// The download operation.
+   pub fn cancel(&self, _app: AppHandle<R>, id: String) -> crate::Result<DownloadActionResponse> {
+      self
+         .0
+         .run_mobile_plugin("cancel", PathArgs { path: id })
+         .map_err(Into::into)
+   }
+
+   ///
+   /// Subscribes a channel to the updates of a download, until the download is completed or cancelled.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID, which is the download path on iOS.
+   /// - `channel` - The channel which receives the updated download.
    ///
    /// # Returns
    /// The download operation.
-   pub fn cancel(&self, _app: AppHandle<R>, path: String) -> crate::Result<DownloadActionResponse> {
+   pub fn subscribe(
+      &self,
+      _app: AppHandle<R>,
+      id: String,
+      channel: Channel<DownloadItem>,
+   ) -> crate::Result<DownloadItem> {
       self
          .0
-         .run_mobile_plugin("cancel", PathArgs { path })
+         .run_mobile_plugin(
+            "subscribe",
+            SubscribeArgs {
+               path: id,
+               on_event: channel,
+            },
+         )
          .map_err(Into::into)
    }
+
+   ///
+   /// Unsubscribes a channel from the updates of a download.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `id` - The download ID, which is the download path on iOS.
+   /// - `channel_id` - The ID of the channel.
+   pub fn unsubscribe(&self, _app: AppHandle<R>, id: String, channel_id: u32) -> crate::Result<()> {
+      self
+         .0
+         .run_mobile_plugin(
+            "unsubscribe",
+            UnsubscribeArgs {
+               path: id,
+               channel_id,
+            },
+         )
+         .map_err(Into::into)
+   }
+
+   ///
+   /// Registers a callback which is invoked with each event in the lifecycle of every download.
+   /// Not supported on iOS, where events are only sent to JavaScript.
+   ///
+   pub fn on_event(
+      &self,
+      _callback: impl Fn(&DownloadEvent) + Send + Sync + 'static,
+   ) -> crate::Result<u64> {
+      Err(Error::Unsupported("on_event".to_string()))
+   }
+
+   ///
+   /// Unregisters a callback registered with `on_event`.
+   /// Not supported on iOS, where events are only sent to JavaScript.
+   ///
+   pub fn remove_event_callback(&self, _id: u64) -> crate::Result<()> {
+      Err(Error::Unsupported("remove_event_callback".to_string()))
+   }
+
+   ///
+   /// Subscribes to the events in the lifecycle of every download.
+   /// Not supported on iOS, where events are only sent to JavaScript.
+   ///
+   pub fn events(&self) -> crate::Result<broadcast::Receiver<DownloadEvent>> {
+      Err(Error::Unsupported("events".to_string()))
+   }
+
+   ///
+   /// Sets the priority of a download operation.
+   /// Not supported on iOS, where downloads are not queued.
+   ///
+   pub fn set_priority(
+      &self,
+      _app: AppHandle<R>,
+      _id: String,
+      _priority: i32,
+   ) -> crate::Result<DownloadItem> {
+      Err(Error::Unsupported("set_priority".to_string()))
+   }
+
+   ///
+   /// Moves a queued download operation to the front of the queue.
+   /// Not supported on iOS, where downloads are not queued.
+   ///
+   pub fn move_to_front(&self, _app: AppHandle<R>, _id: String) -> crate::Result<DownloadItem> {
+      Err(Error::Unsupported("move_to_front".to_string()))
+   }
+
+   ///
+   /// Moves a queued download operation before another queued download operation.
+   /// Not supported on iOS, where downloads are not queued.
+   ///
+   pub fn move_before(
+      &self,
+      _app: AppHandle<R>,
+      _id: String,
+      _before_id: String,
+   ) -> crate::Result<DownloadItem> {
+      Err(Error::Unsupported("move_before".to_string()))
+   }
+
+   ///
+   /// Sets the rate limit of a download operation, or the total rate limit of all download operations.
+   /// Not supported on iOS.
+   ///
+   pub fn set_rate_limit(
+      &self,
+      _app: AppHandle<R>,
+      _id: Option<String>,
+      _rate_limit: Option<u64>,
+   ) -> crate::Result<()> {
+      Err(Error::Unsupported("set_rate_limit".to_string()))
+   }
+
+   ///
+   /// Changes the destination of a download operation. Not supported on iOS.
+   ///
+   pub fn set_destination(
+      &self,
+      _app: AppHandle<R>,
+      _id: String,
+      _path: String,
+   ) -> crate::Result<DownloadItem> {
+      Err(Error::Unsupported("set_destination".to_string()))
+   }
+
+   ///
+   /// Changes the URL of a download operation. Not supported on iOS.
+   ///
+   pub async fn set_url(
+      &self,
+      _app: AppHandle<R>,
+      _id: String,
+      _url: String,
+      _scope: Option<CapabilityScope>,
+   ) -> crate::Result<DownloadItem> {
+      Err(Error::Unsupported("set_url".to_string()))
+   }
+
+   ///
+   /// Gets the finished download operations recorded in the download history. Not supported on iOS.
+   ///
+   pub fn history(&self, _app: AppHandle<R>, _query: HistoryQuery) -> crate::Result<HistoryPage> {
+      Err(Error::Unsupported("history".to_string()))
+   }
+
+   ///
+   /// Removes all download operations from the download history. Not supported on iOS.
+   ///
+   pub fn clear_history(&self, _app: AppHandle<R>) -> crate::Result<()> {
+      Err(Error::Unsupported("clear_history".to_string()))
+   }
+
+   ///
+   /// Removes a download operation from the download history. Not supported on iOS.
+   ///
+   pub fn remove_from_history(&self, _app: AppHandle<R>, _id: String) -> crate::Result<()> {
+      Err(Error::Unsupported("remove_from_history".to_string()))
+   }
+
+   ///
+   /// Sets whether a hook which refreshes the request of a download is registered in JavaScript.
+   /// Not supported on iOS.
+   ///
+   pub fn set_refresh_listener(&self, _label: String, _enabled: bool) -> crate::Result<()> {
+      Err(Error::Unsupported("set_refresh_listener".to_string()))
+   }
+
+   ///
+   /// Unregisters the hooks which refresh the request of a download registered by a webview. Not supported on iOS,
+   /// where no hooks are registered.
+   ///
+   pub(crate) fn remove_refresh_listeners(&self, _label: &str) {}
+
+   ///
+   /// Answers a refresh request from JavaScript. Not supported on iOS.
+   ///
+   pub fn refresh(&self, _request_id: u64, _refresh: Option<DownloadRefresh>) -> crate::Result<()> {
+      Err(Error::Unsupported("refresh".to_string()))
+   }
 }
@@ -1,5 +1,11 @@
+use glob::{MatchOptions, Pattern};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
+#[cfg(any(desktop, target_os = "android"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Error;
 
 #[cfg(mobile)]
 #[derive(Serialize)]
@@ -8,6 +14,22 @@ pub struct PathArgs {
    pub path: String,
 }
 
+#[cfg(mobile)]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscribeArgs {
+   pub path: String,
+   pub on_event: tauri::ipc::Channel<DownloadItem>,
+}
+
+#[cfg(mobile)]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsubscribeArgs {
+   pub path: String,
+   pub channel_id: u32,
+}
+
 #[cfg(mobile)]
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,13 +38,83 @@ pub struct CreateArgs {
    pub url: String,
 }
 
+/// Expected checksum of a download, used to verify its integrity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checksum {
+   pub algorithm: ChecksumAlgorithm,
+   /// The hex digest.
+   pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChecksumAlgorithm {
+   Sha256,
+   Sha1,
+   Md5,
+   Blake3,
+}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadItem {
+   /// The unique ID of the download, generated when it is created. On iOS, the ID is the download path.
+   #[serde(default)]
+   pub id: String,
    pub url: String,
    pub path: String,
    pub progress: f64,
    pub status: DownloadStatus,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub error: Option<DownloadError>,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub retry: Option<DownloadRetry>,
+   /// The position of the download in the queue, while queued.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub queue_position: Option<i64>,
+   /// The priority of the download. Queued downloads with a higher priority are started first.
+   #[serde(default)]
+   pub priority: i32,
+   /// The expected checksum, verified before the download is completed.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub checksum: Option<Checksum>,
+   /// The `ETag` of the resource, used to validate the partial download when resuming.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub etag: Option<String>,
+   /// The `Last-Modified` date of the resource, used to validate the partial download when resuming.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub last_modified: Option<String>,
+   /// Whether the server supports resuming the download, once known.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub resumable: Option<bool>,
+   /// The number of bytes downloaded.
+   #[serde(default)]
+   pub downloaded_bytes: u64,
+   /// The total size of the download in bytes, if known. Progress is indeterminate if the size is unknown.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub total_bytes: Option<u64>,
+   /// The smoothed transfer speed, while in progress.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub bytes_per_second: Option<f64>,
+   /// The estimated time remaining, while in progress and the size is known.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub eta_seconds: Option<f64>,
+   /// The rate limit in bytes per second, if any.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub rate_limit: Option<u64>,
+   /// The request sent to download the resource, if not a plain `GET` request.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub request: Option<DownloadRequest>,
+   /// The segments downloaded in parallel, if the download is segmented.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pub segments: Vec<DownloadSegment>,
+   /// Time the download was first started, in milliseconds since the Unix epoch.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub started_at: Option<u64>,
+   /// The URLs granted to the window which created or started the download, which every URL it fetches must match.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub scope: Option<CapabilityScope>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -32,10 +124,277 @@ pub enum DownloadStatus {
    Unknown,
    Pending,
    Idle,
+   Queued,
    InProgress,
    Paused,
    Cancelled,
    Completed,
+   Failed,
+}
+
+/// The HTTP request sent to download a resource, which is replayed every time the download is resumed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DownloadRequest {
+   /// The HTTP method. Defaults to `GET`.
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub method: Option<String>,
+   /// The request headers, keyed by name.
+   #[serde(skip_serializing_if = "HashMap::is_empty")]
+   pub headers: HashMap<String, String>,
+   /// The names of headers with sensitive values, such as `Authorization`. Sensitive values are only held in memory
+   /// and are never written to the store.
+   #[serde(skip_serializing_if = "Vec::is_empty")]
+   pub sensitive_headers: Vec<String>,
+   /// The query parameters, appended to the URL.
+   #[serde(skip_serializing_if = "HashMap::is_empty")]
+   pub query: HashMap<String, String>,
+   /// The request body.
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub body: Option<String>,
+}
+
+/// An entry of the URL scope granted through the capability system, such as
+/// `{ "url": "https://*.example.com/**" }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlScopeEntry {
+   /// Glob pattern of the URLs.
+   pub url: String,
+}
+
+/// The URLs granted to the calling window through the capability system.
+///
+/// If any URLs are allowed, a download URL must match one of them. Otherwise any URL can be downloaded, including when
+/// only denied URLs are granted. Denied URLs take precedence over allowed URLs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CapabilityScope {
+   pub allow: Vec<UrlScopeEntry>,
+   pub deny: Vec<UrlScopeEntry>,
+}
+
+/// The reason the request of a download is refreshed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RefreshReason {
+   /// A partial download is about to be resumed.
+   Resume,
+   /// The server rejected the request with `401 Unauthorized` or `403 Forbidden`.
+   Unauthorized,
+   /// The values of the sensitive headers are no longer held in memory, such as after the application restarted.
+   MissingHeaders,
+}
+
+/// Request to refresh the request of a download, such as an expired signed URL or bearer token.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshRequest {
+   /// Identifies the request when it is answered from JavaScript.
+   pub request_id: u64,
+   pub reason: RefreshReason,
+   /// The HTTP status which caused the refresh, if any.
+   #[serde(skip_serializing_if = "Option::is_none")]
+   pub http_status: Option<u16>,
+   pub download: DownloadItem,
+}
+
+/// A refreshed request for a download. The download continues from its current offset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DownloadRefresh {
+   /// The new download URL, if changed.
+   pub url: Option<String>,
+   /// The headers to add or replace, keyed by name.
+   pub headers: HashMap<String, String>,
+   /// The names of headers with sensitive values, which are only held in memory.
+   pub sensitive_headers: Vec<String>,
+}
+
+/// A byte range of a segmented download, downloaded in parallel with the other segments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadSegment {
+   /// The offset of the first byte of the segment.
+   pub start: u64,
+   /// The offset of the last byte of the segment (inclusive).
+   pub end: u64,
+   /// The number of bytes of the segment downloaded.
+   pub downloaded: u64,
+}
+
+/// Details of the error which caused a download to fail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadError {
+   pub kind: DownloadErrorKind,
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub http_status: Option<u16>,
+   pub message: String,
+   /// Time the error occurred, in milliseconds since the Unix epoch.
+   pub timestamp: u64,
+}
+
+/// An event in the lifecycle of a download, emitted as `tauri-plugin-download:<type>`, such as
+/// `tauri-plugin-download:completed`. Every event is also emitted as `tauri-plugin-download:changed` with the download.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+   tag = "type",
+   rename_all = "camelCase",
+   rename_all_fields = "camelCase"
+)]
+pub enum DownloadEvent {
+   /// The download was created.
+   Created { download: DownloadItem },
+   /// The download started transferring.
+   Started { download: DownloadItem },
+   /// More bytes of the download were transferred.
+   Progress { download: DownloadItem },
+   /// The download was paused.
+   Paused { download: DownloadItem },
+   /// The download started transferring again, continuing from its partial download.
+   Resumed { download: DownloadItem },
+   /// The download failed with a transient error and will be retried.
+   Retrying {
+      download: DownloadItem,
+      retry: DownloadRetry,
+      error: DownloadError,
+   },
+   /// The download was completed.
+   Completed {
+      download: DownloadItem,
+      /// The size of the downloaded file in bytes.
+      size: u64,
+      /// The time since the download was first started in milliseconds, including any time it was paused.
+      #[serde(default, skip_serializing_if = "Option::is_none")]
+      duration_ms: Option<u64>,
+   },
+   /// The download failed. It can be resumed.
+   Failed {
+      download: DownloadItem,
+      error: DownloadError,
+   },
+   /// The download was cancelled.
+   Cancelled { download: DownloadItem },
+   /// The download was removed from the store, after it was completed or cancelled.
+   Removed { download: DownloadItem },
+}
+
+impl DownloadEvent {
+   ///
+   /// Gets the type of the event, which is the suffix of its event name.
+   ///
+   pub fn kind(&self) -> &'static str {
+      match self {
+         DownloadEvent::Created { .. } => "created",
+         DownloadEvent::Started { .. } => "started",
+         DownloadEvent::Progress { .. } => "progress",
+         DownloadEvent::Paused { .. } => "paused",
+         DownloadEvent::Resumed { .. } => "resumed",
+         DownloadEvent::Retrying { .. } => "retrying",
+         DownloadEvent::Completed { .. } => "completed",
+         DownloadEvent::Failed { .. } => "failed",
+         DownloadEvent::Cancelled { .. } => "cancelled",
+         DownloadEvent::Removed { .. } => "removed",
+      }
+   }
+
+   ///
+   /// Gets the download the event occurred for.
+   ///
+   pub fn download(&self) -> &DownloadItem {
+      match self {
+         DownloadEvent::Created { download }
+         | DownloadEvent::Started { download }
+         | DownloadEvent::Progress { download }
+         | DownloadEvent::Paused { download }
+         | DownloadEvent::Resumed { download }
+         | DownloadEvent::Retrying { download, .. }
+         | DownloadEvent::Completed { download, .. }
+         | DownloadEvent::Failed { download, .. }
+         | DownloadEvent::Cancelled { download }
+         | DownloadEvent::Removed { download } => download,
+      }
+   }
+}
+
+/// A finished download, recorded in the download history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRecord {
+   pub id: String,
+   pub url: String,
+   pub path: String,
+   /// The outcome of the download, which is either completed or cancelled.
+   pub status: DownloadStatus,
+   /// Time the download was first started, in milliseconds since the Unix epoch, if it was started.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub started_at: Option<u64>,
+   /// Time the download was completed or cancelled, in milliseconds since the Unix epoch.
+   pub finished_at: u64,
+   /// The size of the downloaded file in bytes, or the number of bytes downloaded before the download was cancelled.
+   pub size: u64,
+   /// The time since the download was first started in milliseconds, including any time it was paused.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   pub duration_ms: Option<u64>,
+   /// The average speed over the duration of the download in bytes per second, which includes any time it was paused
+   /// so is lower than the transfer speed of a download which was paused.
+   #[serde(
+      default,
+      alias = "bytesPerSecond",
+      skip_serializing_if = "Option::is_none"
+   )]
+   pub average_bytes_per_second: Option<f64>,
+}
+
+/// Filters and paginates the download history.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HistoryQuery {
+   /// Only include downloads with this outcome.
+   pub status: Option<DownloadStatus>,
+   /// Only include downloads finished at or after this time, in milliseconds since the Unix epoch.
+   pub from: Option<u64>,
+   /// Only include downloads finished before this time, in milliseconds since the Unix epoch.
+   pub to: Option<u64>,
+   /// The number of matching downloads to skip.
+   pub offset: usize,
+   /// The maximum number of downloads to return, if any.
+   pub limit: Option<usize>,
+}
+
+/// A page of the download history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+   /// The matching downloads on the page, most recently finished first.
+   pub records: Vec<DownloadRecord>,
+   /// The total number of matching downloads.
+   pub total: usize,
+}
+
+/// Details of a pending retry after a transient error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRetry {
+   /// The retry attempt, starting at 1.
+   pub attempt: u32,
+   pub max_attempts: u32,
+   /// Time of the next retry, in milliseconds since the Unix epoch.
+   pub next_retry_at: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadErrorKind {
+   #[default]
+   Unknown,
+   Network,
+   Http,
+   File,
+   Store,
+   Verification,
+   RangeNotSupported,
+   CertificatePin,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +405,43 @@ pub struct DownloadActionResponse {
    pub is_expected_status: bool,
 }
 
+impl CapabilityScope {
+   ///
+   /// Ensures a URL is allowed. Wildcards do not match across `/`, except for `**`.
+   ///
+   pub fn check(&self, url: &str) -> crate::Result<()> {
+      let options = MatchOptions {
+         require_literal_separator: true,
+         ..MatchOptions::new()
+      };
+      let matches = |entries: &[UrlScopeEntry]| -> crate::Result<bool> {
+         for entry in entries {
+            let pattern = Pattern::new(&entry.url)
+               .map_err(|e| Error::Http(format!("Invalid URL pattern {}: {}", entry.url, e)))?;
+            if pattern.matches_with(url, options) {
+               return Ok(true);
+            }
+         }
+         Ok(false)
+      };
+
+      if (self.allow.is_empty() || matches(&self.allow)?) && !matches(&self.deny)? {
+         Ok(())
+      } else {
+         Err(Error::UrlNotAllowed(url.to_string()))
+      }
+   }
+}
+
+impl DownloadSegment {
+   ///
+   /// Whether all bytes of the segment have been downloaded.
+   ///
+   pub fn is_complete(&self) -> bool {
+      self.start + self.downloaded > self.end
+   }
+}
+
 impl DownloadActionResponse {
    pub fn new(download: DownloadItem) -> Self {
       let expected_status = download.status.clone();
@@ -68,16 +464,47 @@ impl DownloadActionResponse {
 
 #[cfg(any(desktop, target_os = "android"))]
 pub trait DownloadItemExt {
-   fn with_progress(&self, new_progress: f64) -> DownloadItem;
+   fn with_progress(
+      &self,
+      downloaded_bytes: u64,
+      total_bytes: Option<u64>,
+      bytes_per_second: Option<f64>,
+   ) -> DownloadItem;
    fn with_status(&self, new_status: DownloadStatus) -> DownloadItem;
+   fn with_error(&self, error: DownloadError) -> DownloadItem;
+   fn with_retry(&self, retry: DownloadRetry) -> DownloadItem;
+   fn with_queue_position(&self, position: i64) -> DownloadItem;
 }
 
 #[cfg(any(desktop, target_os = "android"))]
 impl DownloadItemExt for DownloadItem {
-   fn with_progress(&self, new_progress: f64) -> DownloadItem {
+   fn with_progress(
+      &self,
+      downloaded_bytes: u64,
+      total_bytes: Option<u64>,
+      bytes_per_second: Option<f64>,
+   ) -> DownloadItem {
+      let progress = match total_bytes {
+         Some(total_bytes) if total_bytes > 0 => {
+            (downloaded_bytes as f64 / total_bytes as f64 * 100.0).min(100.0)
+         }
+         _ => 0.0,
+      };
+      let eta_seconds = match (total_bytes, bytes_per_second) {
+         (Some(total_bytes), Some(speed)) if speed > 0.0 => {
+            Some(total_bytes.saturating_sub(downloaded_bytes) as f64 / speed)
+         }
+         _ => None,
+      };
+
       DownloadItem {
-         progress: new_progress,
+         progress,
          status: DownloadStatus::InProgress,
+         retry: None,
+         downloaded_bytes,
+         total_bytes,
+         bytes_per_second,
+         eta_seconds,
          ..self.clone() // Clone the rest of the fields
       }
    }
@@ -89,10 +516,95 @@ impl DownloadItemExt for DownloadItem {
          } else {
             self.progress
          },
+         error: if new_status == DownloadStatus::Failed {
+            self.error.clone()
+         } else {
+            None
+         },
+         queue_position: if new_status == DownloadStatus::Queued {
+            self.queue_position
+         } else {
+            None
+         },
          status: new_status,
+         retry: None,
+         bytes_per_second: None,
+         eta_seconds: None,
          ..self.clone() // Clone the rest of the fields
       }
    }
+
+   fn with_error(&self, error: DownloadError) -> DownloadItem {
+      DownloadItem {
+         status: DownloadStatus::Failed,
+         error: Some(error),
+         retry: None,
+         bytes_per_second: None,
+         eta_seconds: None,
+         ..self.clone() // Clone the rest of the fields
+      }
+   }
+
+   fn with_retry(&self, retry: DownloadRetry) -> DownloadItem {
+      DownloadItem {
+         status: DownloadStatus::InProgress,
+         retry: Some(retry),
+         bytes_per_second: None,
+         eta_seconds: None,
+         ..self.clone() // Clone the rest of the fields
+      }
+   }
+
+   fn with_queue_position(&self, position: i64) -> DownloadItem {
+      DownloadItem {
+         status: DownloadStatus::Queued,
+         queue_position: Some(position),
+         error: None,
+         retry: None,
+         ..self.clone() // Clone the rest of the fields
+      }
+   }
+}
+
+#[cfg(any(desktop, target_os = "android"))]
+impl From<&Error> for DownloadError {
+   fn from(error: &Error) -> Self {
+      let (kind, http_status) = match error {
+         Error::Network(_) => (DownloadErrorKind::Network, None),
+         Error::Http(_)
+         | Error::InvalidRequest(_)
+         | Error::SensitiveHeadersMissing(_)
+         | Error::UrlNotAllowed(_) => (DownloadErrorKind::Http, None),
+         Error::HttpStatus(status) => (DownloadErrorKind::Http, Some(*status)),
+         Error::File(_) | Error::Io(_) | Error::PathNotAllowed(_) => {
+            (DownloadErrorKind::File, None)
+         }
+         Error::Store(_) => (DownloadErrorKind::Store, None),
+         Error::Checksum(_) => (DownloadErrorKind::Verification, None),
+         Error::RangeNotSupported => (DownloadErrorKind::RangeNotSupported, None),
+         Error::CertificatePin(_) => (DownloadErrorKind::CertificatePin, None),
+         Error::Failed(error) => return error.clone(),
+         _ => (DownloadErrorKind::Unknown, None),
+      };
+
+      Self {
+         kind,
+         http_status,
+         message: error.to_string(),
+         timestamp: now_millis(),
+      }
+   }
+}
+
+///
+/// Gets the current time, in milliseconds since the Unix epoch.
+///
+#[cfg(any(desktop, target_os = "android"))]
+pub fn now_millis() -> u64 {
+   SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|duration| duration.as_millis() as u64)
+      .unwrap_or(0)
 }
 
 #[cfg(any(desktop, target_os = "android"))]
@@ -102,11 +614,111 @@ impl fmt::Display for DownloadStatus {
          DownloadStatus::Unknown => "Unknown",
          DownloadStatus::Pending => "Pending",
          DownloadStatus::Idle => "Idle",
+         DownloadStatus::Queued => "Queued",
          DownloadStatus::InProgress => "InProgress",
          DownloadStatus::Paused => "Paused",
          DownloadStatus::Cancelled => "Cancelled",
          DownloadStatus::Completed => "Completed",
+         DownloadStatus::Failed => "Failed",
       };
       write!(f, "{}", text)
    }
 }
+
+#[cfg(any(desktop, target_os = "android"))]
+impl fmt::Display for ChecksumAlgorithm {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      let text = match self {
+         ChecksumAlgorithm::Sha256 => "SHA-256",
+         ChecksumAlgorithm::Sha1 => "SHA-1",
+         ChecksumAlgorithm::Md5 => "MD5",
+         ChecksumAlgorithm::Blake3 => "BLAKE3",
+      };
+      write!(f, "{}", text)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn entries(urls: &[&str]) -> Vec<UrlScopeEntry> {
+      urls
+         .iter()
+         .map(|url| UrlScopeEntry {
+            url: url.to_string(),
+         })
+         .collect()
+   }
+
+   #[test]
+   fn capability_scope_allows_any_url_without_entries() {
+      let scope = CapabilityScope::default();
+      assert!(scope.check("https://example.com/file.zip").is_ok());
+   }
+
+   #[test]
+   fn capability_scope_wildcards_do_not_match_across_separators() {
+      let scope = CapabilityScope {
+         allow: entries(&["https://*.example.com/*"]),
+         deny: Vec::new(),
+      };
+      assert!(scope.check("https://cdn.example.com/file.zip").is_ok());
+      assert!(
+         scope
+            .check("https://cdn.example.com/files/file.zip")
+            .is_err()
+      );
+      assert!(
+         scope
+            .check("https://evil.org/cdn.example.com/file.zip")
+            .is_err()
+      );
+      assert!(scope.check("http://cdn.example.com/file.zip").is_err());
+
+      let scope = CapabilityScope {
+         allow: entries(&["https://cdn.example.com/**"]),
+         deny: Vec::new(),
+      };
+      assert!(
+         scope
+            .check("https://cdn.example.com/files/file.zip")
+            .is_ok()
+      );
+      assert!(
+         scope
+            .check("https://cdn.example.com:8443/file.zip")
+            .is_err()
+      );
+   }
+
+   #[test]
+   fn capability_scope_denied_urls_take_precedence() {
+      let scope = CapabilityScope {
+         allow: entries(&["https://*.example.com/**"]),
+         deny: entries(&["https://private.example.com/**"]),
+      };
+      assert!(scope.check("https://cdn.example.com/file.zip").is_ok());
+      assert!(scope.check("https://private.example.com/file.zip").is_err());
+   }
+
+   #[test]
+   fn capability_scope_allows_any_url_not_denied_without_allowed_urls() {
+      let scope = CapabilityScope {
+         allow: Vec::new(),
+         deny: entries(&["https://private.example.com/**"]),
+      };
+      assert!(scope.check("https://cdn.example.com/file.zip").is_ok());
+      assert!(scope.check("http://localhost/file.zip").is_ok());
+      assert!(scope.check("https://private.example.com/file.zip").is_err());
+   }
+
+   #[test]
+   fn capability_scope_rejects_invalid_patterns() {
+      let scope = CapabilityScope {
+         allow: entries(&["https://[example.com/**"]),
+         deny: Vec::new(),
+      };
+      assert!(scope.check("https://example.com/file.zip").is_err());
+   }
+}
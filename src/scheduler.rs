@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri_plugin_http::reqwest::Url;
+
+use crate::{Config, DownloadItem};
+
+/// Tracks the running downloads and limits how many can run at once.
+#[derive(Default)]
+pub struct Scheduler {
+   /// The host of each running download, keyed by ID.
+   active: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl Scheduler {
+   ///
+   /// Claims a slot to run a download, if the download is not already running and the concurrency limits allow it.
+   ///
+   /// # Arguments
+   /// - `config` - The plugin configuration.
+   /// - `item` - The download item.
+   ///
+   /// # Returns
+   /// Whether a slot was claimed.
+   pub fn try_acquire(&self, config: &Config, item: &DownloadItem) -> bool {
+      let mut active = self.active.lock().unwrap();
+      if active.contains_key(&item.id) || active.len() >= config.max_concurrent {
+         return false;
+      }
+
+      let host = host(&item.url);
+      if let Some(limit) = host
+         .as_ref()
+         .and_then(|host| config.max_concurrent_per_host.get(host))
+      {
+         let running = active.values().filter(|other| **other == host).count();
+         if running >= *limit {
+            return false;
+         }
+      }
+
+      active.insert(item.id.clone(), host);
+      true
+   }
+
+   ///
+   /// Releases the slot claimed by a download.
+   ///
+   /// # Arguments
+   /// - `id` - The download ID.
+   pub fn release(&self, id: &str) {
+      self.active.lock().unwrap().remove(id);
+   }
+}
+
+fn host(url: &str) -> Option<String> {
+   Url::parse(url)
+      .ok()
+      .and_then(|url| url.host_str().map(str::to_string))
+}
@@ -0,0 +1,478 @@
+use glob::{MatchOptions, Pattern};
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
+use tauri::scope::fs::Scope;
+use tauri::utils::config::FsScope;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_http::reqwest::Url;
+use tauri_plugin_http::reqwest::dns::{Name, Resolve, Resolving};
+
+use crate::{Error, UrlScope, error};
+
+/// Restricts the paths downloads can be written to.
+pub struct PathScope {
+   /// The allowed and denied paths, or `None` if any path is allowed.
+   scope: Option<Scope>,
+}
+
+impl PathScope {
+   ///
+   /// Creates the scope, resolving any path variables such as `$DOWNLOAD` in the allowed and denied paths.
+   ///
+   /// # Arguments
+   /// - `app` - The application handle.
+   /// - `scope` - The allowed and denied paths, or `None` if any path is allowed.
+   pub fn new<R: Runtime>(app: &AppHandle<R>, scope: Option<&FsScope>) -> crate::Result<Self> {
+      let scope = scope
+         .map(|scope| Scope::new(app, scope))
+         .transpose()
+         .map_err(|e| Error::File(format!("Invalid destination scope: {}", e)))?;
+
+      Ok(Self { scope })
+   }
+
+   ///
+   /// Ensures a path is allowed. Relative paths and paths containing `..` are never allowed when a scope is
+   /// configured, and symlinks are resolved so they cannot escape the scope.
+   ///
+   /// # Arguments
+   /// - `path` - The path.
+   pub fn check(&self, path: &str) -> crate::Result<()> {
+      let Some(scope) = &self.scope else {
+         return Ok(());
+      };
+
+      let path = Path::new(path);
+      if path.is_absolute()
+         && !path
+            .components()
+            .any(|component| component == Component::ParentDir)
+         && resolve(path).is_some_and(|path| scope.is_allowed(path))
+      {
+         Ok(())
+      } else {
+         Err(Error::PathNotAllowed(path.display().to_string()))
+      }
+   }
+}
+
+///
+/// Resolves any symlinks in a path which may not exist yet, by canonicalizing its nearest existing ancestor.
+///
+/// # Returns
+/// The resolved path, or `None` if it could not be resolved, such as when the path is a broken symlink.
+fn resolve(path: &Path) -> Option<PathBuf> {
+   let existing = path
+      .ancestors()
+      .find(|ancestor| ancestor.symlink_metadata().is_ok())?;
+   let remainder = path.strip_prefix(existing).ok()?;
+
+   Some(existing.canonicalize().ok()?.join(remainder))
+}
+
+/// Restricts the URLs downloads can fetch, according to the plugin configuration.
+pub struct UrlFilter {
+   schemes: Vec<String>,
+   allow_hosts: Vec<Pattern>,
+   deny_hosts: Vec<Pattern>,
+   ports: Vec<RangeInclusive<u16>>,
+   deny_private_ips: bool,
+}
+
+impl UrlFilter {
+   ///
+   /// Creates the filter, parsing the host patterns and port ranges of the URL scope.
+   ///
+   pub fn new(scope: &UrlScope) -> crate::Result<Self> {
+      let ports = scope
+         .ports
+         .iter()
+         .map(|ports| {
+            let (start, end) = ports.split_once('-').unwrap_or((ports, ports));
+            match (start.trim().parse(), end.trim().parse()) {
+               (Ok(start), Ok(end)) if start <= end => Ok(start..=end),
+               _ => Err(Error::Http(format!("Invalid port range: {}", ports))),
+            }
+         })
+         .collect::<crate::Result<_>>()?;
+
+      Ok(Self {
+         schemes: scope
+            .schemes
+            .iter()
+            .map(|scheme| scheme.to_lowercase())
+            .collect(),
+         allow_hosts: patterns(&scope.allow_hosts)?,
+         deny_hosts: patterns(&scope.deny_hosts)?,
+         ports,
+         deny_private_ips: scope.deny_private_ips,
+      })
+   }
+
+   ///
+   /// Ensures a URL is allowed. Host names are only checked against non-public IP addresses when they are resolved.
+   ///
+   pub fn check(&self, url: &Url) -> crate::Result<()> {
+      let options = MatchOptions {
+         case_sensitive: false,
+         ..MatchOptions::new()
+      };
+      let host = url.host_str().unwrap_or_default();
+      let host_allowed = |host: &str| {
+         (self.allow_hosts.is_empty()
+            || self
+               .allow_hosts
+               .iter()
+               .any(|pattern| pattern.matches_with(host, options)))
+            && !self
+               .deny_hosts
+               .iter()
+               .any(|pattern| pattern.matches_with(host, options))
+      };
+      let port_allowed =
+         |port: u16| self.ports.is_empty() || self.ports.iter().any(|ports| ports.contains(&port));
+      let ip_allowed = || {
+         let ip = host.trim_start_matches('[').trim_end_matches(']');
+         !self.deny_private_ips || ip.parse().map_or(true, is_public)
+      };
+
+      if self.schemes.iter().any(|scheme| scheme == url.scheme())
+         && !host.is_empty()
+         && host_allowed(host)
+         && url.port_or_known_default().is_some_and(port_allowed)
+         && ip_allowed()
+      {
+         Ok(())
+      } else {
+         Err(Error::UrlNotAllowed(url.to_string()))
+      }
+   }
+}
+
+/// Resolves host names, failing for any which resolve to non-public IP addresses.
+pub struct PublicResolver;
+
+/// Error when a host name resolves to a non-public IP address.
+#[derive(Debug)]
+struct NonPublicAddress(String);
+
+impl fmt::Display for NonPublicAddress {
+   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(f, "{} resolves to a non-public IP address", self.0)
+   }
+}
+
+impl StdError for NonPublicAddress {}
+
+impl Resolve for PublicResolver {
+   fn resolve(&self, name: Name) -> Resolving {
+      let host = name.as_str().to_string();
+      Box::pin(async move {
+         let addresses = tokio::net::lookup_host((host.as_str(), 0))
+            .await?
+            .collect::<Vec<_>>();
+         if addresses.iter().any(|address| !is_public(address.ip())) {
+            return Err(NonPublicAddress(host).into());
+         }
+
+         Ok(Box::new(addresses.into_iter()) as _)
+      })
+   }
+}
+
+///
+/// Gets the host which resolved to a non-public IP address, if the request failed for this reason.
+///
+pub fn non_public_address(error: &reqwest::Error) -> Option<String> {
+   error::sources(error).find_map(|error| {
+      error
+         .downcast_ref::<NonPublicAddress>()
+         .map(|NonPublicAddress(host)| host.clone())
+   })
+}
+
+///
+/// Parses glob patterns of hosts.
+///
+fn patterns(patterns: &[String]) -> crate::Result<Vec<Pattern>> {
+   patterns
+      .iter()
+      .map(|pattern| {
+         Pattern::new(pattern)
+            .map_err(|e| Error::Http(format!("Invalid host pattern {}: {}", pattern, e)))
+      })
+      .collect()
+}
+
+///
+/// Whether an IP address is publicly routable, rather than a loopback, private, link-local, shared, documentation or
+/// other special-purpose address.
+///
+fn is_public(ip: IpAddr) -> bool {
+   match ip {
+      IpAddr::V4(ip) => is_public_v4(ip),
+      IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+         Some(ip) => is_public_v4(ip),
+         None => is_public_v6(ip),
+      },
+   }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+   let [a, b, c, _] = ip.octets();
+   !(ip.is_unspecified()
+      || ip.is_loopback()
+      || ip.is_private()
+      || ip.is_link_local()
+      || ip.is_multicast()
+      || ip.is_documentation()
+      || a == 0
+      || a >= 240
+      // Shared address space (100.64.0.0/10).
+      || (a == 100 && (b & 0xc0) == 64)
+      // IETF protocol assignments (192.0.0.0/24).
+      || (a == 192 && b == 0 && c == 0)
+      // Benchmarking (198.18.0.0/15).
+      || (a == 198 && (b & 0xfe) == 18))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+   let segments = ip.segments();
+   // Addresses embedding an IPv4 address for NAT64 (64:ff9b::/96).
+   if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+      let [a, b] = segments[6].to_be_bytes();
+      let [c, d] = segments[7].to_be_bytes();
+      return is_public_v4(Ipv4Addr::new(a, b, c, d));
+   }
+
+   !(ip.is_unspecified()
+      || ip.is_loopback()
+      || ip.is_multicast()
+      || ip.is_unique_local()
+      || ip.is_unicast_link_local()
+      // Documentation (2001:db8::/32).
+      || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use std::fs;
+
+   /// Directory which is removed when dropped.
+   struct TempDir(PathBuf);
+
+   impl TempDir {
+      fn new() -> Self {
+         let path =
+            std::env::temp_dir().join(format!("tauri-plugin-download-{}", uuid::Uuid::new_v4()));
+         fs::create_dir_all(&path).unwrap();
+         Self(path.canonicalize().unwrap())
+      }
+
+      fn path(&self, path: &str) -> String {
+         self.0.join(path).display().to_string()
+      }
+   }
+
+   impl Drop for TempDir {
+      fn drop(&mut self) {
+         let _ = fs::remove_dir_all(&self.0);
+      }
+   }
+
+   fn path_scope(dir: &TempDir, allow: &[&str], deny: &[&str]) -> PathScope {
+      let app = tauri::test::mock_app();
+      let scope = FsScope::Scope {
+         allow: allow
+            .iter()
+            .map(|path| PathBuf::from(dir.path(path)))
+            .collect(),
+         deny: deny
+            .iter()
+            .map(|path| PathBuf::from(dir.path(path)))
+            .collect(),
+         require_literal_leading_dot: None,
+      };
+      PathScope::new(app.handle(), Some(&scope)).unwrap()
+   }
+
+   #[test]
+   fn allows_any_path_without_scope() {
+      let app = tauri::test::mock_app();
+      let scope = PathScope::new(app.handle(), None).unwrap();
+      assert!(scope.check("relative/../file.zip").is_ok());
+   }
+
+   #[test]
+   fn allows_paths_in_scope() {
+      let dir = TempDir::new();
+      fs::create_dir_all(dir.path("allowed")).unwrap();
+      let scope = path_scope(&dir, &["allowed/**"], &[]);
+      assert!(scope.check(&dir.path("allowed/file.zip")).is_ok());
+      assert!(
+         scope
+            .check(&dir.path("allowed/new/folder/file.zip"))
+            .is_ok()
+      );
+      assert!(scope.check(&dir.path("file.zip")).is_err());
+      assert!(scope.check(&dir.path("other/file.zip")).is_err());
+   }
+
+   #[test]
+   fn denies_parent_and_relative_paths() {
+      let dir = TempDir::new();
+      fs::create_dir_all(dir.path("allowed")).unwrap();
+      let scope = path_scope(&dir, &["allowed/**"], &[]);
+      assert!(scope.check(&dir.path("allowed/../file.zip")).is_err());
+      assert!(
+         scope
+            .check(&dir.path("allowed/../allowed/file.zip"))
+            .is_err()
+      );
+      assert!(scope.check("allowed/file.zip").is_err());
+   }
+
+   #[test]
+   fn denied_paths_take_precedence() {
+      let dir = TempDir::new();
+      fs::create_dir_all(dir.path("allowed/private")).unwrap();
+      let scope = path_scope(&dir, &["allowed/**"], &["allowed/private/**"]);
+      assert!(scope.check(&dir.path("allowed/file.zip")).is_ok());
+      assert!(scope.check(&dir.path("allowed/private/file.zip")).is_err());
+      assert!(
+         scope
+            .check(&dir.path("allowed/private/new/file.zip"))
+            .is_err()
+      );
+   }
+
+   #[cfg(unix)]
+   #[test]
+   fn denies_symlinks_out_of_scope() {
+      let dir = TempDir::new();
+      fs::create_dir_all(dir.path("allowed")).unwrap();
+      fs::create_dir_all(dir.path("outside")).unwrap();
+      std::os::unix::fs::symlink(dir.path("outside"), dir.path("allowed/link")).unwrap();
+      std::os::unix::fs::symlink(dir.path("outside/file.zip"), dir.path("allowed/file.zip"))
+         .unwrap();
+      let scope = path_scope(&dir, &["allowed/**"], &[]);
+      assert!(scope.check(&dir.path("allowed/link/file.zip")).is_err());
+      assert!(scope.check(&dir.path("allowed/file.zip")).is_err());
+      assert!(scope.check(&dir.path("allowed/other.zip")).is_ok());
+   }
+
+   fn filter(configure: impl FnOnce(&mut UrlScope)) -> UrlFilter {
+      let mut scope = UrlScope::default();
+      configure(&mut scope);
+      UrlFilter::new(&scope).unwrap()
+   }
+
+   fn allowed(filter: &UrlFilter, url: &str) -> bool {
+      filter.check(&Url::parse(url).unwrap()).is_ok()
+   }
+
+   #[test]
+   fn denies_link_local_and_metadata_addresses() {
+      let filter = filter(|scope| scope.deny_private_ips = true);
+      assert!(!allowed(
+         &filter,
+         "http://169.254.169.254/latest/meta-data/"
+      ));
+      assert!(!allowed(&filter, "http://169.254.0.1/"));
+      assert!(!allowed(&filter, "http://[fe80::1]/"));
+      assert!(!allowed(&filter, "http://[fd00:ec2::254]/"));
+   }
+
+   #[test]
+   fn denies_private_and_special_addresses() {
+      let filter = filter(|scope| scope.deny_private_ips = true);
+      for url in [
+         "http://127.0.0.1/",
+         "http://10.0.0.1/",
+         "http://172.16.0.1/",
+         "http://192.168.1.1/",
+         "http://100.64.0.1/",
+         "http://0.0.0.0/",
+         "http://198.18.0.1/",
+         "http://[::1]/",
+         "http://[::]/",
+      ] {
+         assert!(!allowed(&filter, url), "{} should be denied", url);
+      }
+      assert!(allowed(&filter, "http://93.184.215.14/"));
+      assert!(allowed(&filter, "http://[2606:4700::1111]/"));
+   }
+
+   #[test]
+   fn denies_private_addresses_embedded_in_ipv6() {
+      let filter = filter(|scope| scope.deny_private_ips = true);
+      assert!(!allowed(&filter, "http://[::ffff:127.0.0.1]/"));
+      assert!(!allowed(&filter, "http://[::ffff:169.254.169.254]/"));
+      assert!(!allowed(&filter, "http://[64:ff9b::a9fe:a9fe]/"));
+      assert!(allowed(&filter, "http://[::ffff:93.184.215.14]/"));
+   }
+
+   #[test]
+   fn allows_private_addresses_unless_denied() {
+      let filter = filter(|_| ());
+      assert!(allowed(&filter, "http://127.0.0.1/"));
+      assert!(allowed(&filter, "http://169.254.169.254/"));
+   }
+
+   #[test]
+   fn denies_other_ports() {
+      let filter = filter(|scope| scope.ports = vec!["443".to_string(), "8000-8100".to_string()]);
+      assert!(allowed(&filter, "https://example.com/file.zip"));
+      assert!(allowed(&filter, "http://example.com:8000/file.zip"));
+      assert!(allowed(&filter, "http://example.com:8100/file.zip"));
+      assert!(!allowed(&filter, "http://example.com/file.zip"));
+      assert!(!allowed(&filter, "https://example.com:8443/file.zip"));
+      assert!(!allowed(&filter, "http://example.com:8101/file.zip"));
+   }
+
+   #[test]
+   fn rejects_invalid_port_ranges() {
+      for ports in ["8100-8000", "https", "1-65536"] {
+         let scope = UrlScope {
+            ports: vec![ports.to_string()],
+            ..Default::default()
+         };
+         assert!(
+            UrlFilter::new(&scope).is_err(),
+            "{} should be invalid",
+            ports
+         );
+      }
+   }
+
+   #[test]
+   fn denies_other_schemes() {
+      let default = filter(|_| ());
+      assert!(allowed(&default, "HTTPS://example.com/file.zip"));
+      assert!(!allowed(&default, "ftp://example.com/file.zip"));
+      assert!(!allowed(&default, "file:///etc/passwd"));
+      assert!(!allowed(&default, "data:text/plain,hello"));
+
+      let https = filter(|scope| scope.schemes = vec!["HTTPS".to_string()]);
+      assert!(allowed(&https, "https://example.com/file.zip"));
+      assert!(!allowed(&https, "http://example.com/file.zip"));
+   }
+
+   #[test]
+   fn denied_hosts_take_precedence() {
+      let filter = filter(|scope| {
+         scope.allow_hosts = vec!["*.example.com".to_string()];
+         scope.deny_hosts = vec!["private.example.com".to_string()];
+      });
+      assert!(allowed(&filter, "https://cdn.example.com/file.zip"));
+      assert!(allowed(&filter, "https://CDN.Example.com/file.zip"));
+      assert!(!allowed(&filter, "https://private.example.com/file.zip"));
+      assert!(!allowed(&filter, "https://example.org/file.zip"));
+      assert!(!allowed(&filter, "https://example.com.evil.org/file.zip"));
+   }
+}
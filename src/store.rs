@@ -1,13 +1,16 @@
+use std::sync::{Mutex, MutexGuard};
 use tauri::{AppHandle, Runtime};
 use tauri_plugin_store::StoreExt;
 
-use crate::{DownloadItem, Error};
+use crate::{DownloadExt, DownloadItem, Error};
 
-static DOWNLOAD_STORE_PATH: &str = "downloads.json";
+/// Serializes changes to downloads, so a change based on a download read from the store does not overwrite a
+/// concurrent change, such as the download being paused or cancelled while its progress is recorded.
+static LOCK: Mutex<()> = Mutex::new(());
 
 pub fn list<R: Runtime>(app: &AppHandle<R>) -> crate::Result<Vec<DownloadItem>> {
    let store = app
-      .store(DOWNLOAD_STORE_PATH)
+      .store(&app.download().config().store_path)
       .map_err(|e| Error::Store(format!("Failed to load store: {}", e)))?;
 
    let mut items = Vec::new();
@@ -22,46 +25,47 @@ pub fn list<R: Runtime>(app: &AppHandle<R>) -> crate::Result<Vec<DownloadItem>>
    Ok(items)
 }
 
-pub fn get<R: Runtime>(app: &AppHandle<R>, path: String) -> crate::Result<Option<DownloadItem>> {
+///
+/// Gets a download by its ID or, failing that, by its path.
+///
+pub fn get<R: Runtime>(app: &AppHandle<R>, id: String) -> crate::Result<Option<DownloadItem>> {
    let store = app
-      .store(DOWNLOAD_STORE_PATH)
+      .store(&app.download().config().store_path)
       .map_err(|e| Error::Store(format!("Failed to load store: {}", e)))?;
 
-   match store.get(&path) {
+   match store.get(&id) {
       Some(value) => Ok(Some(serde_json::from_value(value).unwrap())),
-      None => Ok(None),
+      None => Ok(list(app)?.into_iter().find(|item| item.path == id)),
    }
 }
 
 pub fn create<R: Runtime>(app: &AppHandle<R>, item: DownloadItem) -> crate::Result<DownloadItem> {
    let store = app
-      .store(DOWNLOAD_STORE_PATH)
+      .store(&app.download().config().store_path)
       .map_err(|e| Error::Store(format!("Failed to load store: {}", e)))?;
 
-   match store.get(&item.path) {
-      Some(_) => {
-         return Err(Error::Store(format!(
-            "Item already exists for path: {}",
-            &item.path
-         )));
-      }
-      None => {
-         store.set(&item.path, serde_json::to_value(&item).unwrap());
-         store
-            .save()
-            .map_err(|e| Error::Store(format!("Failed to save store: {}", e)))?;
-      }
+   // Only one download can be written to a path at a time, as they would share the partial download.
+   if store.has(&item.id) || list(app)?.iter().any(|existing| existing.path == item.path) {
+      return Err(Error::Store(format!(
+         "Item already exists for path: {}",
+         &item.path
+      )));
    }
 
+   store.set(&item.id, serde_json::to_value(&item).unwrap());
+   store
+      .save()
+      .map_err(|e| Error::Store(format!("Failed to save store: {}", e)))?;
+
    Ok(item)
 }
 
 pub fn update<R: Runtime>(app: &AppHandle<R>, item: DownloadItem) -> crate::Result<()> {
    let store = app
-      .store(DOWNLOAD_STORE_PATH)
+      .store(&app.download().config().store_path)
       .map_err(|e| Error::Store(format!("Failed to load store: {}", e)))?;
 
-   store.set(&item.path, serde_json::to_value(&item).unwrap());
+   store.set(&item.id, serde_json::to_value(&item).unwrap());
    store
       .save()
       .map_err(|e| Error::Store(format!("Failed to save store: {}", e)))?;
@@ -69,9 +73,40 @@ pub fn update<R: Runtime>(app: &AppHandle<R>, item: DownloadItem) -> crate::Resu
    Ok(())
 }
 
+///
+/// Locks the store against concurrent changes. The lock is held from reading a download until writing it back, and
+/// must be released before emitting events or awaiting.
+///
+pub fn lock() -> MutexGuard<'static, ()> {
+   LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+///
+/// Changes a download based on its current state in the store, while the store is locked.
+///
+/// # Arguments
+/// - `app` - The application handle.
+/// - `id` - The download ID or path.
+/// - `change` - Changes the download, or fails if it cannot be changed in its current state.
+///
+/// # Returns
+/// The changed download.
+pub fn modify<R: Runtime>(
+   app: &AppHandle<R>,
+   id: String,
+   change: impl FnOnce(&mut DownloadItem) -> crate::Result<()>,
+) -> crate::Result<DownloadItem> {
+   let _lock = lock();
+   let mut item = get(app, id.clone())?.ok_or(Error::NotFound(id))?;
+   change(&mut item)?;
+   update(app, item.clone())?;
+
+   Ok(item)
+}
+
 pub fn delete<R: Runtime>(app: &AppHandle<R>, key: String) -> crate::Result<()> {
    let store = app
-      .store(DOWNLOAD_STORE_PATH)
+      .store(&app.download().config().store_path)
       .map_err(|e| Error::Store(format!("Failed to load store: {}", e)))?;
 
    if store.has(&key) {
@@ -84,3 +119,138 @@ pub fn delete<R: Runtime>(app: &AppHandle<R>, key: String) -> crate::Result<()>
 
    Ok(())
 }
+
+///
+/// Migrates downloads stored by path, as in earlier versions, to be stored by a generated ID.
+///
+pub fn migrate<R: Runtime>(app: &AppHandle<R>) -> crate::Result<()> {
+   let store = app
+      .store(&app.download().config().store_path)
+      .map_err(|e| Error::Store(format!("Failed to load store: {}", e)))?;
+
+   let mut migrated = false;
+   for key in store.keys() {
+      let Some(value) = store.get(&key) else {
+         continue;
+      };
+      let mut item: DownloadItem = serde_json::from_value(value)
+         .map_err(|e| Error::Store(format!("Failed to parse item: {}", e)))?;
+      if !item.id.is_empty() && item.id == key {
+         continue;
+      }
+
+      if item.id.is_empty() {
+         item.id = new_id();
+      }
+      store.delete(&key);
+      store.set(&item.id, serde_json::to_value(&item).unwrap());
+      migrated = true;
+   }
+
+   if migrated {
+      store
+         .save()
+         .map_err(|e| Error::Store(format!("Failed to save store: {}", e)))?;
+   }
+
+   Ok(())
+}
+
+///
+/// Generates a unique ID for a download.
+///
+pub fn new_id() -> String {
+   uuid::Uuid::new_v4().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use std::path::PathBuf;
+   use tauri::test::{MockRuntime, mock_builder, mock_context, noop_assets};
+
+   /// Store file which is removed when dropped.
+   struct TempStore(PathBuf);
+
+   impl TempStore {
+      fn new() -> Self {
+         Self(std::env::temp_dir().join(format!("tauri-plugin-download-{}.json", new_id())))
+      }
+   }
+
+   impl Drop for TempStore {
+      fn drop(&mut self) {
+         let _ = std::fs::remove_file(&self.0);
+      }
+   }
+
+   fn mock_app(store: &TempStore) -> tauri::App<MockRuntime> {
+      mock_builder()
+         .plugin(tauri_plugin_store::Builder::new().build())
+         .plugin(
+            crate::Builder::new()
+               .store_path(store.0.display().to_string())
+               .build(),
+         )
+         .build(mock_context(noop_assets()))
+         .unwrap()
+   }
+
+   fn legacy(app: &AppHandle<MockRuntime>, path: &str) {
+      let item = DownloadItem {
+         path: path.to_string(),
+         url: "https://example.com/file.zip".to_string(),
+         ..Default::default()
+      };
+      let store = app.store(&app.download().config().store_path).unwrap();
+      store.set(path, serde_json::to_value(&item).unwrap());
+   }
+
+   fn keys(app: &AppHandle<MockRuntime>) -> Vec<String> {
+      let mut keys = app
+         .store(&app.download().config().store_path)
+         .unwrap()
+         .keys();
+      keys.sort();
+      keys
+   }
+
+   #[test]
+   fn migrate_stores_downloads_by_id() {
+      let store = TempStore::new();
+      let app = mock_app(&store);
+      let app = app.handle();
+      legacy(app, "/downloads/file.zip");
+
+      migrate(app).unwrap();
+
+      let item = get(app, "/downloads/file.zip".to_string())
+         .unwrap()
+         .unwrap();
+      assert!(!item.id.is_empty());
+      assert_eq!(item.path, "/downloads/file.zip");
+      assert_eq!(keys(app), vec![item.id.clone()]);
+      let by_id = get(app, item.id.clone()).unwrap().unwrap();
+      assert_eq!(by_id.path, item.path);
+   }
+
+   #[test]
+   fn migrate_is_idempotent() {
+      let store = TempStore::new();
+      let app = mock_app(&store);
+      let app = app.handle();
+      legacy(app, "/downloads/a.zip");
+      legacy(app, "/downloads/b.zip");
+
+      migrate(app).unwrap();
+      let migrated = keys(app);
+      let items = list(app).unwrap();
+      migrate(app).unwrap();
+
+      assert_eq!(keys(app), migrated);
+      for item in items {
+         let current = get(app, item.id.clone()).unwrap().unwrap();
+         assert_eq!((current.id, current.path), (item.id, item.path));
+      }
+   }
+}
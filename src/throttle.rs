@@ -0,0 +1,143 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting the rate at which bytes are downloaded.
+///
+/// The bucket holds up to one second of tokens at the configured rate. Consuming more tokens than are available puts
+/// the bucket into debt, which must be repaid by waiting before further bytes are consumed.
+pub struct RateLimiter {
+   bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+   /// The limit in bytes per second, or `None` if unlimited.
+   rate: Option<u64>,
+   tokens: f64,
+   updated_at: Instant,
+}
+
+impl RateLimiter {
+   pub fn new(rate: Option<u64>) -> Self {
+      Self {
+         bucket: Mutex::new(Bucket {
+            rate,
+            tokens: rate.unwrap_or(0) as f64,
+            updated_at: Instant::now(),
+         }),
+      }
+   }
+
+   ///
+   /// Sets the limit, which applies to any bytes consumed from now on.
+   ///
+   /// # Arguments
+   /// - `rate` - The limit in bytes per second, or `None` if unlimited.
+   pub fn set_rate(&self, rate: Option<u64>) {
+      let mut bucket = self.bucket.lock().unwrap();
+      bucket.rate = rate;
+      bucket.tokens = bucket.tokens.min(rate.unwrap_or(0) as f64);
+      bucket.updated_at = Instant::now();
+   }
+
+   ///
+   /// Consumes tokens for the given number of bytes, waiting until the limit allows them to be consumed.
+   ///
+   pub async fn acquire(&self, bytes: u64) {
+      let delay = self.reserve(bytes);
+      if !delay.is_zero() {
+         tokio::time::sleep(delay).await;
+      }
+   }
+
+   ///
+   /// Consumes tokens for the given number of bytes, returning how long to wait before they may be consumed.
+   ///
+   fn reserve(&self, bytes: u64) -> Duration {
+      let mut bucket = self.bucket.lock().unwrap();
+      let Some(rate) = bucket.rate.filter(|rate| *rate > 0) else {
+         return Duration::ZERO;
+      };
+
+      // Refill the bucket for the time elapsed since it was last updated.
+      let now = Instant::now();
+      let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+      bucket.tokens = (bucket.tokens + elapsed * rate as f64).min(rate as f64);
+      bucket.updated_at = now;
+
+      bucket.tokens -= bytes as f64;
+      if bucket.tokens >= 0.0 {
+         Duration::ZERO
+      } else {
+         Duration::from_secs_f64(-bucket.tokens / rate as f64)
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   /// Moves the last update of the bucket back in time, as if the time had elapsed.
+   fn elapse(limiter: &RateLimiter, seconds: f64) {
+      let mut bucket = limiter.bucket.lock().unwrap();
+      bucket.updated_at -= Duration::from_secs_f64(seconds);
+   }
+
+   fn assert_delay(delay: Duration, seconds: f64) {
+      assert!(
+         (delay.as_secs_f64() - seconds).abs() < 0.05,
+         "{:?} is not {}s",
+         delay,
+         seconds
+      );
+   }
+
+   #[test]
+   fn reserve_is_unlimited_without_rate() {
+      for rate in [None, Some(0)] {
+         let limiter = RateLimiter::new(rate);
+         assert_eq!(limiter.reserve(u64::MAX), Duration::ZERO);
+         assert_eq!(limiter.reserve(u64::MAX), Duration::ZERO);
+      }
+   }
+
+   #[test]
+   fn reserve_allows_burst_of_one_second() {
+      let limiter = RateLimiter::new(Some(1_000));
+      assert_eq!(limiter.reserve(600), Duration::ZERO);
+      assert_eq!(limiter.reserve(400), Duration::ZERO);
+      assert_delay(limiter.reserve(500), 0.5);
+      assert_delay(limiter.reserve(1_000), 1.5);
+   }
+
+   #[test]
+   fn reserve_refills_over_time() {
+      let limiter = RateLimiter::new(Some(1_000));
+      assert_eq!(limiter.reserve(1_000), Duration::ZERO);
+      assert_delay(limiter.reserve(1_000), 1.0);
+
+      // The debt is repaid before tokens become available again.
+      elapse(&limiter, 1.5);
+      assert_eq!(limiter.reserve(400), Duration::ZERO);
+      assert_delay(limiter.reserve(200), 0.1);
+   }
+
+   #[test]
+   fn reserve_caps_refill_at_rate() {
+      let limiter = RateLimiter::new(Some(1_000));
+      elapse(&limiter, 10.0);
+      assert_eq!(limiter.reserve(1_000), Duration::ZERO);
+      assert_delay(limiter.reserve(1_000), 1.0);
+   }
+
+   #[test]
+   fn set_rate_limits_available_tokens() {
+      let limiter = RateLimiter::new(Some(1_000));
+      limiter.set_rate(Some(100));
+      assert_eq!(limiter.reserve(100), Duration::ZERO);
+      assert_delay(limiter.reserve(50), 0.5);
+
+      limiter.set_rate(None);
+      assert_eq!(limiter.reserve(u64::MAX), Duration::ZERO);
+   }
+}
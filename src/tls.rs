@@ -0,0 +1,187 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::server::ParsedCertificate;
+use rustls::{
+   CertificateError, ClientConfig, DigitallySignedStruct, OtherError, RootCertStore,
+   SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+use tauri_plugin_http::reqwest;
+
+use crate::{Config, Error, error};
+
+/// Verifies server certificates against the trusted roots, then against the pinned public keys of the host (if any).
+#[derive(Debug)]
+struct PinningVerifier {
+   inner: Arc<WebPkiServerVerifier>,
+   /// The SHA-256 hashes of the pinned public keys, keyed by host name.
+   pins: HashMap<String, Vec<Vec<u8>>>,
+}
+
+/// Error when a server certificate does not match the pinned public keys of the host.
+#[derive(Debug)]
+struct PinMismatch(String);
+
+impl fmt::Display for PinMismatch {
+   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      write!(
+         f,
+         "Certificate does not match the pinned keys for {}",
+         self.0
+      )
+   }
+}
+
+impl StdError for PinMismatch {}
+
+impl ServerCertVerifier for PinningVerifier {
+   fn verify_server_cert(
+      &self,
+      end_entity: &CertificateDer<'_>,
+      intermediates: &[CertificateDer<'_>],
+      server_name: &ServerName<'_>,
+      ocsp_response: &[u8],
+      now: UnixTime,
+   ) -> Result<ServerCertVerified, rustls::Error> {
+      let verified = self.inner.verify_server_cert(
+         end_entity,
+         intermediates,
+         server_name,
+         ocsp_response,
+         now,
+      )?;
+
+      let host = server_name.to_str().to_lowercase();
+      if let Some(pins) = self.pins.get(&host) {
+         let spki = ParsedCertificate::try_from(end_entity)?.subject_public_key_info();
+         let hash = Sha256::digest(spki.as_ref());
+         if !pins.iter().any(|pin| pin.as_slice() == hash.as_slice()) {
+            let error = PinMismatch(host);
+            return Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+               OtherError(Arc::new(error)),
+            )));
+         }
+      }
+
+      Ok(verified)
+   }
+
+   fn verify_tls12_signature(
+      &self,
+      message: &[u8],
+      cert: &CertificateDer<'_>,
+      dss: &DigitallySignedStruct,
+   ) -> Result<HandshakeSignatureValid, rustls::Error> {
+      self.inner.verify_tls12_signature(message, cert, dss)
+   }
+
+   fn verify_tls13_signature(
+      &self,
+      message: &[u8],
+      cert: &CertificateDer<'_>,
+      dss: &DigitallySignedStruct,
+   ) -> Result<HandshakeSignatureValid, rustls::Error> {
+      self.inner.verify_tls13_signature(message, cert, dss)
+   }
+
+   fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+      self.inner.supported_verify_schemes()
+   }
+}
+
+///
+/// Creates the TLS configuration which trusts the additional root certificates and verifies the pinned public keys,
+/// if any are configured.
+///
+/// # Arguments
+/// - `config` - The plugin configuration.
+///
+/// # Returns
+/// The TLS configuration, or `None` if the default configuration can be used.
+pub fn client_config(config: &Config) -> crate::Result<Option<ClientConfig>> {
+   if config.root_certificates.is_empty() && config.certificate_pins.is_empty() {
+      return Ok(None);
+   }
+
+   let mut roots = RootCertStore {
+      roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+   };
+   for certificate in &config.root_certificates {
+      for certificate in certificates(certificate)? {
+         roots
+            .add(certificate)
+            .map_err(|e| Error::Http(format!("Invalid root certificate: {}", e)))?;
+      }
+   }
+
+   let mut pins = HashMap::new();
+   for (host, host_pins) in &config.certificate_pins {
+      let host_pins = host_pins
+         .iter()
+         .map(|pin| {
+            STANDARD
+               .decode(pin.strip_prefix("sha256/").unwrap_or(pin))
+               .ok()
+               .filter(|hash| hash.len() == 32)
+               .ok_or_else(|| Error::Http(format!("Invalid certificate pin for {}: {}", host, pin)))
+         })
+         .collect::<crate::Result<Vec<_>>>()?;
+      pins.insert(host.to_lowercase(), host_pins);
+   }
+
+   let provider = Arc::new(ring::default_provider());
+   let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+      .build()
+      .map_err(|e| Error::Http(format!("Failed to create certificate verifier: {}", e)))?;
+
+   let mut tls = ClientConfig::builder_with_provider(provider)
+      .with_safe_default_protocol_versions()
+      .map_err(|e| Error::Http(format!("Failed to create TLS configuration: {}", e)))?
+      .dangerous()
+      .with_custom_certificate_verifier(Arc::new(PinningVerifier { inner, pins }))
+      .with_no_client_auth();
+
+   // Negotiate HTTP/2 as reqwest does for its own TLS configuration, as a preconfigured one is used as is.
+   tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+   Ok(Some(tls))
+}
+
+///
+/// Gets the host whose pinned public keys did not match its certificate, if the request failed for this reason.
+///
+pub fn pin_mismatch(error: &reqwest::Error) -> Option<String> {
+   error::sources(error).find_map(|error| match error.downcast_ref::<rustls::Error>() {
+      Some(rustls::Error::InvalidCertificate(CertificateError::Other(other))) => other
+         .0
+         .downcast_ref::<PinMismatch>()
+         .map(|PinMismatch(host)| host.clone()),
+      _ => None,
+   })
+}
+
+///
+/// Gets the certificates of a PEM encoded certificate bundle, or of a path to a PEM file.
+///
+fn certificates(certificate: &str) -> crate::Result<Vec<CertificateDer<'static>>> {
+   let pem = if certificate.trim_start().starts_with("-----BEGIN") {
+      certificate.as_bytes().to_vec()
+   } else {
+      fs::read(certificate)
+         .map_err(|e| Error::File(format!("Failed to read certificate {}: {}", certificate, e)))?
+   };
+
+   CertificateDer::pem_slice_iter(&pem)
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|e| Error::Http(format!("Invalid root certificate: {}", e)))
+}